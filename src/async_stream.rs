@@ -0,0 +1,504 @@
+//! Asynchronous streaming, wrapping bladerf_init_stream / bladerf_stream
+//!
+//! [`BladeRF::rx_async`] and [`BladeRF::tx_async`] run `bladerf_stream` on a
+//! dedicated thread, calling a Rust closure for every buffer. libbladeRF
+//! allocates and owns the sample buffers, closures only ever borrow them for
+//! the duration of a call, and `bladerf_deinit_stream` is called exactly once
+//! when the stream thread exits.
+
+use std::any::Any;
+use std::ffi::c_void;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::Waker;
+use std::thread::{self, JoinHandle};
+
+use bladerf_sys::*;
+
+use crate::{
+    sample, BladeRF, BladeRfError, ChannelLayout, Direction, ErrorKind, Sample, StreamConfig,
+    SyncFormat,
+};
+
+/// Returned from a stream callback to end the stream (BLADERF_STREAM_SHUTDOWN)
+const STREAM_SHUTDOWN: *mut c_void = ptr::null_mut();
+
+/// Action requested by an asynchronous stream callback
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum StreamAction {
+    /// Keep streaming, resubmitting the buffer
+    Continue,
+    /// End the stream, for TX the current buffer is not transmitted
+    Stop,
+}
+
+/// State shared between a stream thread, its handle and the device
+pub(crate) struct Shared {
+    stop: AtomicBool,
+    thread: Mutex<Option<JoinHandle<Result<(), BladeRfError>>>>,
+    /// Woken on stop, for threads waiting on an async queue
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Shared {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self {
+            stop: AtomicBool::new(false),
+            thread: Mutex::new(None),
+            waker: Mutex::new(None),
+        })
+    }
+
+    /// Track a started stream thread, so the device can stop it before closing
+    pub(crate) fn track(
+        self: &Arc<Self>,
+        device: &BladeRF,
+        thread: JoinHandle<Result<(), BladeRfError>>,
+    ) {
+        *self.thread.lock().unwrap_or_else(|e| e.into_inner()) = Some(thread);
+        device
+            .async_streams
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(self.clone());
+    }
+
+    /// Stop tracking the stream, once its thread has been joined
+    pub(crate) fn untrack(self: &Arc<Self>, device: &BladeRF) {
+        device
+            .async_streams
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|s| !Arc::ptr_eq(s, self));
+    }
+
+    /// Check whether the stream has been asked to stop
+    pub(crate) fn stopped(&self) -> bool {
+        self.stop.load(Ordering::SeqCst)
+    }
+
+    /// Register a waker to be woken when the stream is stopped
+    ///
+    /// Register before checking [`Shared::stopped`] so a stop is never missed.
+    #[cfg(feature = "async")]
+    pub(crate) fn register(&self, waker: &Waker) {
+        *self.waker.lock().unwrap_or_else(|e| e.into_inner()) = Some(waker.clone());
+    }
+
+    /// Signal the stream to stop and wait for its thread, if still running
+    pub(crate) fn join(&self, stop: bool) -> Option<thread::Result<Result<(), BladeRfError>>> {
+        if stop {
+            self.stop.store(true, Ordering::SeqCst);
+            if let Some(w) = self.waker.lock().unwrap_or_else(|e| e.into_inner()).take() {
+                w.wake();
+            }
+        }
+
+        let handle = self.thread.lock().unwrap_or_else(|e| e.into_inner()).take();
+        handle.map(|h| h.join())
+    }
+}
+
+/// Callback state, passed to libbladeRF as user data
+struct Context<F> {
+    callback: F,
+    shared: Arc<Shared>,
+    /// Buffer array allocated by bladerf_init_stream
+    buffers: *mut *mut c_void,
+    num_buffers: usize,
+    samples_per_buffer: usize,
+    /// Next unused buffer, handed out when TX requests its initial buffers
+    next_buffer: usize,
+    /// Panic raised by the callback, resumed once the stream has been torn down
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+/// Stream callback trampoline
+///
+/// Safety: `user_data` must point to a valid `Context<F>`, and `samples` must
+/// be null or one of the context's buffers, holding samples of type `T`.
+unsafe extern "C" fn stream_callback<T, F>(
+    _dev: *mut bladerf,
+    _stream: *mut bladerf_stream,
+    _meta: *mut bladerf_metadata,
+    samples: *mut c_void,
+    num_samples: usize,
+    user_data: *mut c_void,
+) -> *mut c_void
+where
+    T: Sample,
+    F: FnMut(&mut [T]) -> StreamAction,
+{
+    let ctx = &mut *(user_data as *mut Context<F>);
+    if ctx.panic.is_some() || ctx.shared.stopped() {
+        return STREAM_SHUTDOWN;
+    }
+
+    // TX requests its initial buffers without samples, hand out unused ones
+    let (buffer, len) = if samples.is_null() {
+        if ctx.next_buffer >= ctx.num_buffers {
+            return STREAM_SHUTDOWN;
+        }
+        let buffer = *ctx.buffers.add(ctx.next_buffer);
+        ctx.next_buffer += 1;
+        (buffer, ctx.samples_per_buffer)
+    } else {
+        (samples, num_samples)
+    };
+
+    // A count beyond the allocated buffers would read out of bounds
+    if len > ctx.samples_per_buffer {
+        return STREAM_SHUTDOWN;
+    }
+
+    let data = slice::from_raw_parts_mut(buffer as *mut T, len);
+
+    // Unwinding across the FFI boundary is undefined, so catch and defer panics
+    let callback = &mut ctx.callback;
+    match panic::catch_unwind(AssertUnwindSafe(|| callback(data))) {
+        Ok(StreamAction::Continue) => buffer,
+        Ok(StreamAction::Stop) => {
+            ctx.shared.stop.store(true, Ordering::SeqCst);
+            STREAM_SHUTDOWN
+        }
+        Err(e) => {
+            ctx.panic = Some(e);
+            STREAM_SHUTDOWN
+        }
+    }
+}
+
+/// Initialised libbladeRF stream, deinitialised exactly once on drop
+struct StreamTask<F> {
+    stream: *mut bladerf_stream,
+    context: *mut Context<F>,
+    layout: ChannelLayout,
+}
+
+// Safety: the task is moved to the stream thread before use, and libbladeRF
+// streams may be run from any thread.
+unsafe impl<F: Send> Send for StreamTask<F> {}
+
+impl<F> StreamTask<F> {
+    /// Run the stream until it is shut down, returning any callback panic
+    fn run(&self) -> (Result<(), BladeRfError>, Option<Box<dyn Any + Send>>) {
+        let res = unsafe { bladerf_stream(self.stream, self.layout as bladerf_channel_layout) };
+
+        // Safety: the callback no longer runs once bladerf_stream has returned
+        let panic = unsafe { (*self.context).panic.take() };
+
+        let res = match res {
+            r if r < 0 => Err(BladeRfError::from_code(r, "bladerf_stream")),
+            _ => Ok(()),
+        };
+        (res, panic)
+    }
+}
+
+impl<F> Drop for StreamTask<F> {
+    fn drop(&mut self) {
+        // Safety: the stream and context are owned by this task, and
+        // deinit frees the buffer array the context points into
+        unsafe {
+            bladerf_deinit_stream(self.stream);
+            drop(Box::from_raw(self.context));
+        }
+    }
+}
+
+/// Running asynchronous stream
+///
+/// The stream is stopped, its thread joined and its channels disabled when
+/// dropped. Dropping the device also stops any remaining streams.
+pub struct AsyncStream<'a> {
+    device: &'a BladeRF,
+    layout: ChannelLayout,
+    shared: Arc<Shared>,
+}
+
+impl<'a> AsyncStream<'a> {
+    /// Initialise a stream, enable its channels and start the stream thread
+    pub(crate) fn start<T, F>(
+        device: &'a BladeRF,
+        direction: Direction,
+        config: StreamConfig,
+        callback: F,
+    ) -> Result<Self, BladeRfError>
+    where
+        T: Sample,
+        F: FnMut(&mut [T]) -> StreamAction + Send + 'static,
+    {
+        let op = "AsyncStream::start";
+        let layout = config.layout(direction);
+
+        if config.format != T::FORMAT {
+            sample::check::<T>(config.format, op)?;
+            return Err(BladeRfError::new(ErrorKind::Unsupported, op)
+                .with_detail(format!("unsupported format {:?}", config.format)));
+        }
+        if device.streams[direction as usize].load(Ordering::SeqCst) {
+            return Err(BladeRfError::new(ErrorKind::Inval, op)
+                .with_detail(format!("{} stream already active", direction)));
+        }
+
+        device.set_stream_timeout(direction, config.timeout_ms)?;
+
+        let shared = Shared::new();
+
+        let context = Box::into_raw(Box::new(Context {
+            callback,
+            shared: shared.clone(),
+            buffers: ptr::null_mut(),
+            num_buffers: config.num_buffers as usize,
+            samples_per_buffer: config.buffer_size as usize,
+            next_buffer: 0,
+            panic: None,
+        }));
+
+        let mut stream = ptr::null_mut();
+        let mut buffers = ptr::null_mut();
+        let res = unsafe {
+            bladerf_init_stream(
+                &mut stream,
+                device.device,
+                Some(stream_callback::<T, F>),
+                &mut buffers,
+                config.num_buffers as usize,
+                config.format as bladerf_format,
+                config.buffer_size as usize,
+                config.num_transfers as usize,
+                context as *mut c_void,
+            )
+        };
+        if res < 0 {
+            // Safety: the context was not retained by libbladeRF
+            drop(unsafe { Box::from_raw(context) });
+            return Err(BladeRfError::from_code(res, "bladerf_init_stream"));
+        }
+
+        // From here dropping the task tears the stream down
+        unsafe { (*context).buffers = buffers };
+        let task = StreamTask {
+            stream,
+            context,
+            layout,
+        };
+
+        // Claim the direction, racing with streams started on other threads
+        if device.streams[direction as usize]
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Err(BladeRfError::new(ErrorKind::Inval, op)
+                .with_detail(format!("{} stream already active", direction)));
+        }
+
+        // The sync calls are rejected while the direction streams asynchronously
+        *device.lock_sync_format(direction) = SyncFormat::Unconfigured;
+
+        // Drop disables any channels enabled before a failure
+        let handle = Self {
+            device,
+            layout,
+            shared,
+        };
+
+        for channel in layout.channels() {
+            device.enable_module(channel, true)?;
+        }
+
+        let thread = thread::Builder::new()
+            .name(format!("bladerf-{}", direction).to_lowercase())
+            .spawn(move || {
+                let (res, panic) = task.run();
+                drop(task);
+
+                if let Some(e) = panic {
+                    panic::resume_unwind(e);
+                }
+                res
+            })
+            .map_err(|e| BladeRfError::new(ErrorKind::Unexpected, op).with_detail(e.to_string()))?;
+
+        handle.shared.track(device, thread);
+
+        Ok(handle)
+    }
+
+    pub fn layout(&self) -> ChannelLayout {
+        self.layout
+    }
+
+    /// Check whether the stream is still running
+    pub fn is_running(&self) -> bool {
+        let thread = self.shared.thread.lock().unwrap_or_else(|e| e.into_inner());
+        matches!(&*thread, Some(h) if !h.is_finished())
+    }
+
+    /// Stop the stream and wait for it to finish
+    ///
+    /// Panics raised by the callback are resumed here.
+    pub fn stop(mut self) -> Result<(), BladeRfError> {
+        self.finish(true)
+    }
+
+    /// Wait for the callback to end the stream, or for the stream to fail
+    ///
+    /// Panics raised by the callback are resumed here.
+    pub fn wait(mut self) -> Result<(), BladeRfError> {
+        self.finish(false)
+    }
+
+    fn finish(&mut self, stop: bool) -> Result<(), BladeRfError> {
+        let res = self.shutdown(stop);
+        match res {
+            Some(Err(e)) => panic::resume_unwind(e),
+            Some(Ok(r)) => r,
+            None => Ok(()),
+        }
+    }
+
+    /// Join the stream thread, then disable the channels and release the direction
+    fn shutdown(&mut self, stop: bool) -> Option<thread::Result<Result<(), BladeRfError>>> {
+        let res = self.shared.join(stop);
+        self.shared.untrack(self.device);
+
+        let direction = self.layout.direction();
+        if self.device.streams[direction as usize].swap(false, Ordering::SeqCst) {
+            for channel in self.layout.channels() {
+                let _ = self.device.enable_module(channel, false);
+            }
+        }
+
+        res
+    }
+}
+
+impl Drop for AsyncStream<'_> {
+    fn drop(&mut self) {
+        let _ = self.shutdown(true);
+    }
+}
+
+impl std::fmt::Debug for AsyncStream<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncStream")
+            .field("layout", &self.layout)
+            .field("running", &self.is_running())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_complex::Complex;
+
+    use super::*;
+
+    /// Build a callback context over test buffers
+    fn context<F>(callback: F, buffers: &mut [*mut c_void], len: usize) -> Context<F> {
+        Context {
+            callback,
+            shared: Shared::new(),
+            buffers: buffers.as_mut_ptr(),
+            num_buffers: buffers.len(),
+            samples_per_buffer: len,
+            next_buffer: 0,
+            panic: None,
+        }
+    }
+
+    fn call<F>(ctx: &mut Context<F>, samples: *mut c_void) -> *mut c_void
+    where
+        F: FnMut(&mut [Complex<i16>]) -> StreamAction,
+    {
+        let user_data = ctx as *mut Context<F> as *mut c_void;
+        unsafe {
+            stream_callback::<Complex<i16>, F>(
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                samples,
+                ctx.samples_per_buffer,
+                user_data,
+            )
+        }
+    }
+
+    #[test]
+    fn test_rx_callback() {
+        let mut data = vec![vec![Complex::new(1i16, -1i16); 4]; 2];
+        let mut buffers: Vec<_> = data
+            .iter_mut()
+            .map(|b| b.as_mut_ptr() as *mut c_void)
+            .collect();
+
+        let mut count = 0;
+        let mut ctx = context(
+            move |samples: &mut [Complex<i16>]| {
+                assert_eq!(samples.len(), 4);
+                assert_eq!(samples[0], Complex::new(1, -1));
+                count += 1;
+                match count {
+                    1 => StreamAction::Continue,
+                    _ => StreamAction::Stop,
+                }
+            },
+            &mut buffers,
+            4,
+        );
+
+        // Filled buffers are resubmitted until the callback stops
+        let b = buffers[1];
+        assert_eq!(call(&mut ctx, b), b);
+        assert_eq!(call(&mut ctx, b), STREAM_SHUTDOWN);
+        assert!(ctx.shared.stopped());
+
+        // Once stopped the callback is not invoked again
+        assert_eq!(call(&mut ctx, b), STREAM_SHUTDOWN);
+    }
+
+    #[test]
+    fn test_tx_initial_buffers() {
+        let mut data = vec![vec![Complex::new(0i16, 0i16); 2]; 2];
+        let mut buffers: Vec<_> = data
+            .iter_mut()
+            .map(|b| b.as_mut_ptr() as *mut c_void)
+            .collect();
+
+        let mut ctx = context(
+            |samples: &mut [Complex<i16>]| {
+                samples.fill(Complex::new(7, 7));
+                StreamAction::Continue
+            },
+            &mut buffers,
+            2,
+        );
+
+        assert_eq!(call(&mut ctx, ptr::null_mut()), buffers[0]);
+        assert_eq!(call(&mut ctx, ptr::null_mut()), buffers[1]);
+        assert_eq!(call(&mut ctx, ptr::null_mut()), STREAM_SHUTDOWN);
+        drop(ctx);
+
+        assert!(data.iter().flatten().all(|s| *s == Complex::new(7, 7)));
+    }
+
+    #[test]
+    fn test_callback_panic() {
+        let mut data = vec![Complex::new(0i16, 0i16); 2];
+        let mut buffers = vec![data.as_mut_ptr() as *mut c_void];
+
+        let mut ctx = context(
+            |_: &mut [Complex<i16>]| -> StreamAction { panic!("callback failed") },
+            &mut buffers,
+            2,
+        );
+
+        assert_eq!(call(&mut ctx, buffers[0]), STREAM_SHUTDOWN);
+        let e = ctx.panic.take().unwrap();
+        assert_eq!(e.downcast_ref::<&str>(), Some(&"callback failed"));
+    }
+}
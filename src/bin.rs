@@ -9,6 +9,6 @@ pub fn main() {
     println!("Discovered {} devices", devices.len());
 
     for d in devices {
-        println!("Device: {} Serial: {}", d.instance, d.serial());
+        println!("Device: {}", d);
     }
 }
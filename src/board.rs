@@ -0,0 +1,165 @@
+//! Board model detection and capabilities
+
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+
+use crate::{BladeRFLoopback, GainMode};
+
+/// Board model
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Board {
+    /// bladeRF 1 (x40 / x115), LMS6002D based
+    BladeRf1,
+    /// bladeRF 2.0 micro (xA4 / xA5 / xA9), AD9361 based
+    BladeRf2,
+}
+
+/// Static description of what a board supports
+#[derive(Clone, PartialEq, Debug)]
+pub struct Capabilities {
+    /// Number of RX channels
+    pub rx_channels: usize,
+    /// Number of TX channels
+    pub tx_channels: usize,
+    /// RX tuning range in Hz
+    pub rx_frequency: RangeInclusive<u64>,
+    /// TX tuning range in Hz
+    pub tx_frequency: RangeInclusive<u64>,
+    /// Sample rate range in samples per second
+    pub sample_rate: RangeInclusive<u32>,
+    /// Bandwidth range in Hz
+    pub bandwidth: RangeInclusive<u32>,
+    /// Supported loopback modes
+    pub loopbacks: &'static [BladeRFLoopback],
+    /// Supported RX gain modes
+    pub gain_modes: &'static [GainMode],
+    /// Whether the LMS6002D LNA / VGA gain controls are available
+    /// (`set_lna_gain`, `set_rxvga1`, `set_txvga2` etc.)
+    pub lms_gain: bool,
+    /// Whether the ADC sampling source can be selected (`set_sampling`)
+    pub sampling_select: bool,
+}
+
+impl Capabilities {
+    /// Check whether a loopback mode is supported
+    pub fn supports_loopback(&self, loopback: BladeRFLoopback) -> bool {
+        self.loopbacks.contains(&loopback)
+    }
+
+    /// Check whether an RX gain mode is supported
+    pub fn supports_gain_mode(&self, mode: GainMode) -> bool {
+        self.gain_modes.contains(&mode)
+    }
+}
+
+impl Board {
+    /// Name of the board as reported by bladerf_get_board_name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::BladeRf1 => "bladerf1",
+            Self::BladeRf2 => "bladerf2",
+        }
+    }
+
+    /// Fetch the capabilities of this board
+    pub fn capabilities(&self) -> Capabilities {
+        match self {
+            Self::BladeRf1 => Capabilities {
+                rx_channels: 1,
+                tx_channels: 1,
+                rx_frequency: 237_500_000..=3_800_000_000,
+                tx_frequency: 237_500_000..=3_800_000_000,
+                sample_rate: 80_000..=40_000_000,
+                bandwidth: 1_500_000..=28_000_000,
+                loopbacks: &[
+                    BladeRFLoopback::None,
+                    BladeRFLoopback::Firmware,
+                    BladeRFLoopback::BbTxlpfRxvga2,
+                    BladeRFLoopback::BbTxvga1Rxvga2,
+                    BladeRFLoopback::BbTxlpfRxlpf,
+                    BladeRFLoopback::BbTxvga1Rxlpf,
+                    BladeRFLoopback::RfLna1,
+                    BladeRFLoopback::RfLna2,
+                    BladeRFLoopback::RfLna3,
+                ],
+                gain_modes: &[GainMode::Default, GainMode::Manual],
+                lms_gain: true,
+                sampling_select: true,
+            },
+            Self::BladeRf2 => Capabilities {
+                rx_channels: 2,
+                tx_channels: 2,
+                rx_frequency: 70_000_000..=6_000_000_000,
+                tx_frequency: 46_875_000..=6_000_000_000,
+                sample_rate: 520_834..=61_440_000,
+                bandwidth: 200_000..=56_000_000,
+                loopbacks: &[
+                    BladeRFLoopback::None,
+                    BladeRFLoopback::Firmware,
+                    BladeRFLoopback::RficBist,
+                ],
+                gain_modes: &[
+                    GainMode::Default,
+                    GainMode::Manual,
+                    GainMode::FastAttackAgc,
+                    GainMode::SlowAttackAgc,
+                    GainMode::HybridAgc,
+                ],
+                lms_gain: false,
+                sampling_select: false,
+            },
+        }
+    }
+}
+
+impl TryFrom<&str> for Board {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, ()> {
+        match value {
+            "bladerf1" => Ok(Self::BladeRf1),
+            "bladerf2" => Ok(Self::BladeRf2),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BladeRf1 => write!(f, "bladeRF 1"),
+            Self::BladeRf2 => write!(f, "bladeRF 2.0 micro"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_board_names() {
+        for b in [Board::BladeRf1, Board::BladeRf2] {
+            assert_eq!(Board::try_from(b.name()), Ok(b));
+        }
+        assert!(Board::try_from("bladerf3").is_err());
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let rf1 = Board::BladeRf1.capabilities();
+        assert!(rf1.supports_loopback(BladeRFLoopback::BbTxlpfRxlpf));
+        assert!(!rf1.supports_loopback(BladeRFLoopback::RficBist));
+        assert!(!rf1.supports_gain_mode(GainMode::FastAttackAgc));
+        assert!(rf1.rx_frequency.contains(&915_000_000));
+
+        let rf2 = Board::BladeRf2.capabilities();
+        assert_eq!(rf2.rx_channels, 2);
+        assert!(rf2.supports_loopback(BladeRFLoopback::RficBist));
+        assert!(rf2.supports_gain_mode(GainMode::SlowAttackAgc));
+        assert!(!rf2.rx_frequency.contains(&50_000_000));
+        assert!(rf2.sample_rate.contains(&61_440_000));
+    }
+}
@@ -0,0 +1,206 @@
+//! Channel, direction and channel layout types
+
+use std::fmt::Display;
+
+use bladerf_sys::*;
+
+/// Stream direction
+///
+/// wraps bladerf_direction
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(u32)]
+pub enum Direction {
+    Rx = bladerf_direction_BLADERF_RX,
+    Tx = bladerf_direction_BLADERF_TX,
+}
+
+impl TryFrom<bladerf_direction> for Direction {
+    type Error = bladerf_direction;
+
+    fn try_from(value: bladerf_direction) -> Result<Self, bladerf_direction> {
+        let v = match value {
+            bladerf_direction_BLADERF_RX => Self::Rx,
+            bladerf_direction_BLADERF_TX => Self::Tx,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rx => write!(f, "RX"),
+            Self::Tx => write!(f, "TX"),
+        }
+    }
+}
+
+/// RF channel, identified by direction and zero based index
+///
+/// Equivalent to the BLADERF_CHANNEL_RX(n) / BLADERF_CHANNEL_TX(n) macros
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Channel {
+    pub direction: Direction,
+    pub index: u8,
+}
+
+impl Channel {
+    pub const fn new(direction: Direction, index: u8) -> Self {
+        Self { direction, index }
+    }
+
+    /// RX channel with the provided index
+    pub const fn rx(index: u8) -> Self {
+        Self::new(Direction::Rx, index)
+    }
+
+    /// TX channel with the provided index
+    pub const fn tx(index: u8) -> Self {
+        Self::new(Direction::Tx, index)
+    }
+
+    pub fn is_rx(&self) -> bool {
+        self.direction == Direction::Rx
+    }
+
+    pub fn is_tx(&self) -> bool {
+        self.direction == Direction::Tx
+    }
+}
+
+impl From<Channel> for bladerf_channel {
+    fn from(ch: Channel) -> Self {
+        ((ch.index as bladerf_channel) << 1) | ch.direction as bladerf_channel
+    }
+}
+
+impl TryFrom<bladerf_channel> for Channel {
+    type Error = bladerf_channel;
+
+    fn try_from(value: bladerf_channel) -> Result<Self, bladerf_channel> {
+        let index = u8::try_from(value >> 1).map_err(|_| value)?;
+        let direction = match value & 1 {
+            0 => Direction::Rx,
+            _ => Direction::Tx,
+        };
+
+        Ok(Self::new(direction, index))
+    }
+}
+
+impl Display for Channel {
+    /// Channels are displayed one based, as in libbladeRF (RX(0) is "RX1")
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.direction, self.index as u16 + 1)
+    }
+}
+
+/// Stream channel layout
+///
+/// wraps bladerf_channel_layout
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(u32)]
+pub enum ChannelLayout {
+    /// One RX channel (x1 mode)
+    RxX1 = bladerf_channel_layout_BLADERF_RX_X1,
+    /// One TX channel (x1 mode)
+    TxX1 = bladerf_channel_layout_BLADERF_TX_X1,
+    /// Two RX channels, interleaved (x2 mode)
+    RxX2 = bladerf_channel_layout_BLADERF_RX_X2,
+    /// Two TX channels, interleaved (x2 mode)
+    TxX2 = bladerf_channel_layout_BLADERF_TX_X2,
+}
+
+impl ChannelLayout {
+    /// Direction of the stream
+    pub fn direction(&self) -> Direction {
+        match self {
+            Self::RxX1 | Self::RxX2 => Direction::Rx,
+            Self::TxX1 | Self::TxX2 => Direction::Tx,
+        }
+    }
+
+    /// Number of channels in the stream
+    pub fn num_channels(&self) -> usize {
+        match self {
+            Self::RxX1 | Self::TxX1 => 1,
+            Self::RxX2 | Self::TxX2 => 2,
+        }
+    }
+
+    /// Channels in the stream
+    pub fn channels(&self) -> impl Iterator<Item = Channel> {
+        let direction = self.direction();
+        (0..self.num_channels() as u8).map(move |i| Channel::new(direction, i))
+    }
+}
+
+impl TryFrom<bladerf_channel_layout> for ChannelLayout {
+    type Error = bladerf_channel_layout;
+
+    fn try_from(value: bladerf_channel_layout) -> Result<Self, bladerf_channel_layout> {
+        let v = match value {
+            bladerf_channel_layout_BLADERF_RX_X1 => Self::RxX1,
+            bladerf_channel_layout_BLADERF_TX_X1 => Self::TxX1,
+            bladerf_channel_layout_BLADERF_RX_X2 => Self::RxX2,
+            bladerf_channel_layout_BLADERF_TX_X2 => Self::TxX2,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_macros() {
+        // BLADERF_CHANNEL_RX(n) = n << 1, BLADERF_CHANNEL_TX(n) = (n << 1) | 1
+        assert_eq!(bladerf_channel::from(Channel::rx(0)), 0);
+        assert_eq!(bladerf_channel::from(Channel::tx(0)), 1);
+        assert_eq!(bladerf_channel::from(Channel::rx(1)), 2);
+        assert_eq!(bladerf_channel::from(Channel::tx(1)), 3);
+
+        for raw in 0..8 {
+            assert_eq!(bladerf_channel::from(Channel::try_from(raw).unwrap()), raw);
+        }
+        assert!(Channel::try_from(-1).is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Channel::rx(0).to_string(), "RX1");
+        assert_eq!(Channel::tx(1).to_string(), "TX2");
+    }
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(ChannelLayout::RxX2.direction(), Direction::Rx);
+        assert_eq!(ChannelLayout::TxX2.num_channels(), 2);
+        assert_eq!(
+            ChannelLayout::RxX2.channels().collect::<Vec<_>>(),
+            vec![Channel::rx(0), Channel::rx(1)]
+        );
+        assert_eq!(
+            ChannelLayout::try_from(bladerf_channel_layout_BLADERF_TX_X1),
+            Ok(ChannelLayout::TxX1)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let json = serde_json::to_value(Channel::tx(1)).unwrap();
+        assert_eq!(json, serde_json::json!({"direction": "tx", "index": 1}));
+        assert_eq!(serde_json::to_value(ChannelLayout::RxX2).unwrap(), "rx_x2");
+    }
+}
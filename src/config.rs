@@ -0,0 +1,732 @@
+//! Channel configuration objects and configuration errors
+
+use std::error::Error;
+use std::fmt::Display;
+
+use crate::{
+    Bandwidth, BladeRFLoopback, BladeRfError, Board, Channel, Correction, Decibels, Direction,
+    ErrorKind, GainMode, Hertz, LpfMode, RxMux, SampleRate, TuningMode,
+};
+
+// BladeRF module config object
+//
+// Optional settings are left unchanged when `None`, and are `None` in
+// snapshots when the board does not support them.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct BladeRFModuleConfig {
+    pub frequency: Hertz,
+    pub sample_rate: SampleRate,
+    pub bandwidth: Bandwidth,
+    /// Overall channel gain, distributed across the gain stages by libbladeRF
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub gain: Option<Decibels>,
+    /// LNA gain, bladeRF 1 RX only, applied after `gain`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub lna_gain: Option<Decibels>,
+    /// RXVGA1 / TXVGA1 gain, bladeRF 1 only, applied after `gain`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub vga1: Option<Decibels>,
+    /// RXVGA2 / TXVGA2 gain, bladeRF 1 only, applied after `gain`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub vga2: Option<Decibels>,
+    /// Gain control mode, RX only
+    ///
+    /// Gains are only applied when the mode is left unchanged or set to manual.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub gain_mode: Option<GainMode>,
+    /// Low pass filter mode, bladeRF 1 only
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub lpf_mode: Option<LpfMode>,
+    /// Bias tee state, bladeRF 2.0 only
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub bias_tee: Option<bool>,
+    /// IQ and DC offset corrections
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub corrections: Option<Corrections>,
+}
+
+impl BladeRFModuleConfig {
+    fn gain_mut(&mut self, setting: Setting) -> Option<&mut Option<Decibels>> {
+        match setting {
+            Setting::Gain => Some(&mut self.gain),
+            Setting::LnaGain => Some(&mut self.lna_gain),
+            Setting::Vga1 => Some(&mut self.vga1),
+            Setting::Vga2 => Some(&mut self.vga2),
+            _ => None,
+        }
+    }
+
+    /// Fetch the gain value for a gain setting, `None` when left unchanged
+    pub(crate) fn gain(&self, setting: Setting) -> Option<Decibels> {
+        match setting {
+            Setting::Gain => self.gain,
+            Setting::LnaGain => self.lna_gain,
+            Setting::Vga1 => self.vga1,
+            Setting::Vga2 => self.vga2,
+            _ => None,
+        }
+    }
+
+    /// Update the gain value for a gain setting
+    pub(crate) fn set_gain(&mut self, setting: Setting, gain: Decibels) {
+        if let Some(g) = self.gain_mut(setting) {
+            *g = Some(gain);
+        }
+    }
+
+    /// Check whether manual gains apply, they are skipped when switching to AGC
+    pub(crate) fn manual_gain(&self) -> bool {
+        self.gain_mode.map_or(true, |m| m == GainMode::Manual)
+    }
+
+    /// Settings in `target` that differ from this configuration
+    ///
+    /// Optional settings that are `None` in `target` are left unchanged so never differ.
+    pub fn diff(&self, target: &BladeRFModuleConfig) -> Vec<Setting> {
+        let mut changes = vec![];
+        let mut cmp = |setting, differs: bool| {
+            if differs {
+                changes.push(setting);
+            }
+        };
+
+        cmp(Setting::Frequency, self.frequency != target.frequency);
+        cmp(Setting::SampleRate, self.sample_rate != target.sample_rate);
+        cmp(Setting::Bandwidth, self.bandwidth != target.bandwidth);
+        if target.manual_gain() {
+            for gain in Setting::GAINS {
+                cmp(gain, changed(self.gain(gain), target.gain(gain)));
+            }
+        }
+        cmp(Setting::GainMode, changed(self.gain_mode, target.gain_mode));
+        cmp(Setting::LpfMode, changed(self.lpf_mode, target.lpf_mode));
+        cmp(Setting::BiasTee, changed(self.bias_tee, target.bias_tee));
+        if let Some(corrections) = target.corrections {
+            for corr in Corrections::ALL {
+                let current = self.corrections.map(|c| c.get(corr));
+                cmp(
+                    Setting::Correction(corr),
+                    current != Some(corrections.get(corr)),
+                );
+            }
+        }
+
+        changes
+    }
+}
+
+/// Check whether an optional setting would change
+fn changed<T: PartialEq>(current: Option<T>, target: Option<T>) -> bool {
+    target.is_some() && target != current
+}
+
+/// IQ and DC offset correction values for a channel
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct Corrections {
+    pub dc_offset_i: i16,
+    pub dc_offset_q: i16,
+    pub phase: i16,
+    pub gain: i16,
+}
+
+impl Corrections {
+    /// All corrections, in the order they are applied
+    pub const ALL: [Correction; 4] = [
+        Correction::DcOffI,
+        Correction::DcOffQ,
+        Correction::Phase,
+        Correction::Gain,
+    ];
+
+    /// Fetch a correction value
+    pub fn get(&self, correction: Correction) -> i16 {
+        match correction {
+            Correction::DcOffI => self.dc_offset_i,
+            Correction::DcOffQ => self.dc_offset_q,
+            Correction::Phase => self.phase,
+            Correction::Gain => self.gain,
+        }
+    }
+
+    /// Update a correction value
+    pub fn set(&mut self, correction: Correction, value: i16) {
+        match correction {
+            Correction::DcOffI => self.dc_offset_i = value,
+            Correction::DcOffQ => self.dc_offset_q = value,
+            Correction::Phase => self.phase = value,
+            Correction::Gain => self.gain = value,
+        }
+    }
+}
+
+// BladeRF overall config object
+//
+// Device wide settings are left unchanged when `None`.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct BladeRFConfig {
+    pub tx: BladeRFModuleConfig,
+    pub rx: BladeRFModuleConfig,
+    /// Second TX channel, MIMO boards only
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub tx2: Option<BladeRFModuleConfig>,
+    /// Second RX channel, MIMO boards only
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub rx2: Option<BladeRFModuleConfig>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub loopback: Option<BladeRFLoopback>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub rx_mux: Option<RxMux>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub tuning_mode: Option<TuningMode>,
+}
+
+impl BladeRFConfig {
+    /// Settings in `target` that differ from this configuration
+    ///
+    /// Channel settings are reported with their channel, device wide settings with `None`.
+    /// Optional settings that are `None` in `target` are left unchanged so never differ.
+    pub fn diff(&self, target: &BladeRFConfig) -> Vec<(Option<Channel>, Setting)> {
+        let modules = [
+            (Channel::rx(0), Some(&self.rx), Some(&target.rx)),
+            (Channel::tx(0), Some(&self.tx), Some(&target.tx)),
+            (Channel::rx(1), self.rx2.as_ref(), target.rx2.as_ref()),
+            (Channel::tx(1), self.tx2.as_ref(), target.tx2.as_ref()),
+        ];
+
+        let mut changes = vec![];
+        for (channel, current, target) in modules {
+            if let Some(target) = target {
+                let current = current.cloned().unwrap_or_default();
+                changes.extend(current.diff(target).into_iter().map(|s| (Some(channel), s)));
+            }
+        }
+
+        if changed(self.tuning_mode, target.tuning_mode) {
+            changes.push((None, Setting::TuningMode));
+        }
+        if changed(self.rx_mux, target.rx_mux) {
+            changes.push((None, Setting::RxMux));
+        }
+        if changed(self.loopback, target.loopback) {
+            changes.push((None, Setting::Loopback));
+        }
+
+        changes
+    }
+
+    /// Load a configuration from a TOML string
+    #[cfg(feature = "serde")]
+    pub fn from_toml(s: &str) -> Result<Self, BladeRfError> {
+        toml::from_str(s).map_err(|e| {
+            BladeRfError::new(ErrorKind::Inval, "BladeRFConfig::from_toml")
+                .with_detail(e.to_string())
+        })
+    }
+
+    /// Load a configuration from a JSON string
+    #[cfg(feature = "serde")]
+    pub fn from_json(s: &str) -> Result<Self, BladeRfError> {
+        serde_json::from_str(s).map_err(|e| {
+            BladeRfError::new(ErrorKind::Inval, "BladeRFConfig::from_json")
+                .with_detail(e.to_string())
+        })
+    }
+}
+
+/// Individual setting of a [`BladeRFConfig`]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Setting {
+    Frequency,
+    SampleRate,
+    Bandwidth,
+    Gain,
+    LnaGain,
+    Vga1,
+    Vga2,
+    GainMode,
+    LpfMode,
+    BiasTee,
+    Correction(Correction),
+    Loopback,
+    RxMux,
+    TuningMode,
+}
+
+impl Setting {
+    /// Gain settings, in the order they are applied
+    pub const GAINS: [Setting; 4] = [Self::Gain, Self::LnaGain, Self::Vga1, Self::Vga2];
+
+    /// Name of the corresponding config field
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Frequency => "frequency",
+            Self::SampleRate => "sample_rate",
+            Self::Bandwidth => "bandwidth",
+            Self::Gain => "gain",
+            Self::LnaGain => "lna_gain",
+            Self::Vga1 => "vga1",
+            Self::Vga2 => "vga2",
+            Self::GainMode => "gain_mode",
+            Self::LpfMode => "lpf_mode",
+            Self::BiasTee => "bias_tee",
+            Self::Correction(Correction::DcOffI) => "corrections.dc_offset_i",
+            Self::Correction(Correction::DcOffQ) => "corrections.dc_offset_q",
+            Self::Correction(Correction::Phase) => "corrections.phase",
+            Self::Correction(Correction::Gain) => "corrections.gain",
+            Self::Loopback => "loopback",
+            Self::RxMux => "rx_mux",
+            Self::TuningMode => "tuning_mode",
+        }
+    }
+}
+
+impl Display for Setting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Where a gain setting is applied
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum GainTarget {
+    /// Overall channel gain (`set_gain`)
+    Overall,
+    /// Named gain stage (`set_gain_stage`)
+    Stage(&'static str),
+}
+
+/// Gain settings applied for a board and direction
+pub(crate) fn gain_model(board: Board, direction: Direction) -> &'static [(Setting, GainTarget)] {
+    match (board, direction) {
+        (Board::BladeRf1, Direction::Rx) => &[
+            (Setting::Gain, GainTarget::Overall),
+            (Setting::LnaGain, GainTarget::Stage("lna")),
+            (Setting::Vga1, GainTarget::Stage("rxvga1")),
+            (Setting::Vga2, GainTarget::Stage("rxvga2")),
+        ],
+        (Board::BladeRf1, Direction::Tx) => &[
+            (Setting::Gain, GainTarget::Overall),
+            (Setting::Vga1, GainTarget::Stage("txvga1")),
+            (Setting::Vga2, GainTarget::Stage("txvga2")),
+        ],
+        (Board::BladeRf2, _) => &[(Setting::Gain, GainTarget::Overall)],
+    }
+}
+
+/// Record a failed setting, returning the value on success
+pub(crate) fn record<T>(
+    failures: &mut Vec<(Setting, BladeRfError)>,
+    setting: Setting,
+    res: Result<T, BladeRfError>,
+) -> Option<T> {
+    match res {
+        Ok(v) => Some(v),
+        Err(e) => {
+            failures.push((setting, e));
+            None
+        }
+    }
+}
+
+/// Treat unsupported settings as absent
+pub(crate) fn supported<T>(res: Result<T, BladeRfError>) -> Result<Option<T>, BladeRfError> {
+    match res {
+        Ok(v) => Ok(Some(v)),
+        Err(e) if e.kind() == ErrorKind::Unsupported => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Error returned when a configuration could not be applied
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ConfigError {
+    /// The device could not be queried
+    Device(BladeRfError),
+    /// One or more settings were out of range or could not be applied
+    Settings {
+        /// Operation that applied the settings
+        op: &'static str,
+        failures: Vec<(Setting, BladeRfError)>,
+    },
+}
+
+impl ConfigError {
+    /// Fetch the settings that failed, empty for device errors
+    pub fn failures(&self) -> &[(Setting, BladeRfError)] {
+        match self {
+            Self::Device(_) => &[],
+            Self::Settings { failures, .. } => failures,
+        }
+    }
+}
+
+impl From<BladeRfError> for ConfigError {
+    fn from(e: BladeRfError) -> Self {
+        Self::Device(e)
+    }
+}
+
+impl From<ConfigError> for BladeRfError {
+    /// Collapse into the first failure, with every failed setting in the detail
+    fn from(e: ConfigError) -> Self {
+        match e {
+            ConfigError::Device(e) => e,
+            ConfigError::Settings { op, failures } => {
+                let (kind, channel) = match failures.first() {
+                    Some((_, e)) => (e.kind(), e.channel()),
+                    None => (ErrorKind::Unexpected, None),
+                };
+
+                let mut err = BladeRfError::new(kind, op)
+                    .with_detail(ConfigError::Settings { op, failures }.to_string());
+                if let Some(ch) = channel {
+                    err = err.with_channel(ch);
+                }
+                err
+            }
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Device(e) => write!(f, "{}", e),
+            Self::Settings { failures, .. } => {
+                write!(f, "failed to apply {} setting(s)", failures.len())?;
+                for (i, (setting, e)) in failures.iter().enumerate() {
+                    let sep = if i == 0 { ": " } else { "; " };
+                    write!(f, "{}{} ({})", sep, setting, e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Device(e) => Some(e),
+            Self::Settings { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::{db, mhz, msps};
+
+    #[test]
+    fn test_gain_model() {
+        let rf1_rx = gain_model(Board::BladeRf1, Direction::Rx);
+        assert_eq!(rf1_rx.len(), 4);
+        assert_eq!(rf1_rx[0], (Setting::Gain, GainTarget::Overall));
+        assert_eq!(rf1_rx[1], (Setting::LnaGain, GainTarget::Stage("lna")));
+
+        let rf1_tx = gain_model(Board::BladeRf1, Direction::Tx);
+        assert!(rf1_tx.iter().all(|(s, _)| *s != Setting::LnaGain));
+
+        // The LNA gain is never used as the overall gain
+        for d in [Direction::Rx, Direction::Tx] {
+            assert_eq!(
+                gain_model(Board::BladeRf2, d),
+                &[(Setting::Gain, GainTarget::Overall)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_error() {
+        let failures = vec![
+            (
+                Setting::Frequency,
+                BladeRfError::new(ErrorKind::Range, "BladeRF::configure_module")
+                    .with_channel(Channel::rx(0)),
+            ),
+            (
+                Setting::Vga2,
+                BladeRfError::new(ErrorKind::Inval, "bladerf_set_gain_stage"),
+            ),
+        ];
+        let err = ConfigError::Settings {
+            op: "BladeRF::apply_config",
+            failures,
+        };
+
+        assert_eq!(err.failures().len(), 2);
+        let s = err.to_string();
+        assert!(s.starts_with("failed to apply 2 setting(s): frequency ("));
+        assert!(s.contains("; vga2 ("));
+
+        let e = BladeRfError::from(err);
+        assert_eq!(e.kind(), ErrorKind::Range);
+        assert_eq!(e.op(), "BladeRF::apply_config");
+        assert_eq!(e.channel(), Some(Channel::rx(0)));
+        assert!(e.detail().unwrap().contains("vga2"));
+    }
+
+    #[test]
+    fn test_corrections() {
+        let mut c = Corrections::default();
+        for (i, corr) in Corrections::ALL.into_iter().enumerate() {
+            c.set(corr, i as i16 + 1);
+        }
+        assert_eq!(
+            c,
+            Corrections {
+                dc_offset_i: 1,
+                dc_offset_q: 2,
+                phase: 3,
+                gain: 4,
+            }
+        );
+        assert_eq!(c.get(Correction::Phase), 3);
+        assert_eq!(
+            Setting::Correction(Correction::Gain).to_string(),
+            "corrections.gain"
+        );
+    }
+
+    #[test]
+    fn test_supported() {
+        let unsupported: Result<u32, _> = Err(BladeRfError::new(ErrorKind::Unsupported, "op"));
+        assert_eq!(supported(unsupported), Ok(None));
+        assert_eq!(supported(Ok(1)), Ok(Some(1)));
+
+        let failed: Result<u32, _> = Err(BladeRfError::new(ErrorKind::Io, "op"));
+        assert!(supported(failed).is_err());
+    }
+
+    fn module() -> BladeRFModuleConfig {
+        BladeRFModuleConfig {
+            frequency: mhz(915),
+            sample_rate: msps(2),
+            bandwidth: Bandwidth::khz(1500),
+            lna_gain: Some(db(6)),
+            vga1: Some(db(20)),
+            vga2: Some(db(10)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_module_diff() {
+        let current = BladeRFModuleConfig {
+            gain_mode: Some(GainMode::Manual),
+            corrections: Some(Corrections::default()),
+            ..module()
+        };
+        assert!(current.diff(&module()).is_empty());
+        assert!(current.diff(&current).is_empty());
+
+        let target = BladeRFModuleConfig {
+            frequency: mhz(868),
+            vga2: Some(db(12)),
+            gain_mode: Some(GainMode::SlowAttackAgc),
+            bias_tee: Some(false),
+            corrections: Some(Corrections {
+                phase: 10,
+                ..Default::default()
+            }),
+            ..current.clone()
+        };
+        // Gains are ignored when switching to AGC
+        assert_eq!(
+            current.diff(&target),
+            vec![
+                Setting::Frequency,
+                Setting::GainMode,
+                Setting::BiasTee,
+                Setting::Correction(Correction::Phase),
+            ]
+        );
+
+        let manual = BladeRFModuleConfig {
+            gain_mode: Some(GainMode::Manual),
+            ..target
+        };
+        assert_eq!(current.diff(&manual)[1], Setting::Vga2);
+    }
+
+    #[test]
+    fn test_config_diff() {
+        let current = BladeRFConfig {
+            rx: module(),
+            tx: module(),
+            rx2: Some(module()),
+            loopback: Some(BladeRFLoopback::None),
+            tuning_mode: Some(TuningMode::Host),
+            ..Default::default()
+        };
+
+        let target = BladeRFConfig {
+            tx: BladeRFModuleConfig {
+                sample_rate: msps(4),
+                ..module()
+            },
+            rx2: None,
+            tx2: Some(module()),
+            loopback: Some(BladeRFLoopback::Firmware),
+            ..current.clone()
+        };
+
+        let changes = current.diff(&target);
+        assert!(changes.contains(&(Some(Channel::tx(0)), Setting::SampleRate)));
+        assert!(changes.contains(&(None, Setting::Loopback)));
+        assert!(!changes.contains(&(None, Setting::TuningMode)));
+        assert!(changes.iter().all(|(ch, _)| *ch != Some(Channel::rx(1))));
+        // Channels missing from the current state differ in every set value
+        assert!(changes.contains(&(Some(Channel::tx(1)), Setting::Frequency)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_from_toml_without_gains() {
+        let config = BladeRFConfig::from_toml(
+            r#"
+            [rx]
+            frequency = "915 MHz"
+            sample_rate = "2 Msps"
+            bandwidth = "1.5 MHz"
+
+            [tx]
+            frequency = "915 MHz"
+            sample_rate = "2 Msps"
+            bandwidth = "1.5 MHz"
+            "#,
+        )
+        .unwrap();
+
+        for setting in Setting::GAINS {
+            assert_eq!(config.rx.gain(setting), None);
+            assert_eq!(config.tx.gain(setting), None);
+        }
+
+        // Missing gains leave the current gains untouched
+        let current = BladeRFConfig {
+            rx: module(),
+            tx: module(),
+            ..Default::default()
+        };
+        assert!(current.diff(&config).is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_from_toml() {
+        let config = BladeRFConfig::from_toml(
+            r#"
+            tuning_mode = "fpga"
+
+            [rx]
+            frequency = "915 MHz"
+            sample_rate = "2 Msps"
+            bandwidth = "1.5M"
+            lna_gain = "6 dB"
+            vga1 = 20
+            vga2 = "10dB"
+            gain_mode = "manual"
+
+            [tx]
+            frequency = 915000000
+            sample_rate = "2M"
+            bandwidth = "1.5 MHz"
+            bias_tee = true
+
+            [tx.corrections]
+            phase = 10
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.rx,
+            BladeRFModuleConfig {
+                gain_mode: Some(GainMode::Manual),
+                ..module()
+            }
+        );
+        assert_eq!(config.tx.frequency, mhz(915));
+        assert_eq!(config.tx.vga1, None);
+        assert_eq!(config.tx.bias_tee, Some(true));
+        assert_eq!(config.tx.corrections.unwrap().phase, 10);
+        assert_eq!(config.tuning_mode, Some(TuningMode::Fpga));
+        assert_eq!(config.loopback, None);
+        assert_eq!(config.rx2, None);
+
+        let err = BladeRFConfig::from_toml("[rx]\nfrequency = \"915 MHz\"").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Inval);
+
+        let typo = "[rx]\nfrequency = 1\nsample_rate = 1\nbandwidth = 1\nvga3 = 1\n[tx]";
+        assert!(BladeRFConfig::from_toml(typo).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let config = BladeRFConfig {
+            rx: BladeRFModuleConfig {
+                lpf_mode: Some(LpfMode::Bypassed),
+                ..module()
+            },
+            tx: module(),
+            rx_mux: Some(RxMux::Counter32Bit),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(json.contains(r#""frequency":"915 MHz""#));
+        assert!(json.contains(r#""rx_mux":"counter32_bit""#));
+        assert!(!json.contains("tx2"));
+
+        assert_eq!(BladeRFConfig::from_json(&json).unwrap(), config);
+        assert!(BladeRFConfig::from_json("{}").is_err());
+    }
+}
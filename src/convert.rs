@@ -0,0 +1,262 @@
+//! Conversion between sample formats
+//!
+//! Integer samples are scaled so full scale maps to ±1.0: SC16 Q11 by 2048 and
+//! SC8 Q7 by 128. Conversions to integers round to nearest, ties to even, and
+//! saturate to the valid range, [-2048, 2047] for SC16 Q11 and [-128, 127] for
+//! SC8 Q7. NaN converts to zero.
+//!
+//! The slice conversions are simple per-element loops without branches, which
+//! the compiler vectorizes.
+
+use std::slice;
+
+use num_complex::Complex;
+
+use crate::Sample;
+
+/// SC16 Q11 full scale
+const SC16_SCALE: f32 = 2048.0;
+/// SC8 Q7 full scale
+const SC8_SCALE: f32 = 128.0;
+
+/// Conversion of a sample to another representation
+pub trait Convert<T> {
+    fn convert(self) -> T;
+}
+
+/// Scale, round and saturate a component, NaN is left for the cast to zero
+#[inline(always)]
+fn quantize(v: f32, scale: f32, max: f32) -> f32 {
+    (v * scale).round_ties_even().clamp(-scale, max)
+}
+
+impl Convert<Complex<f32>> for Complex<i16> {
+    #[inline(always)]
+    fn convert(self) -> Complex<f32> {
+        Complex::new(
+            self.re as f32 * (1.0 / SC16_SCALE),
+            self.im as f32 * (1.0 / SC16_SCALE),
+        )
+    }
+}
+
+impl Convert<Complex<f32>> for Complex<i8> {
+    #[inline(always)]
+    fn convert(self) -> Complex<f32> {
+        Complex::new(
+            self.re as f32 * (1.0 / SC8_SCALE),
+            self.im as f32 * (1.0 / SC8_SCALE),
+        )
+    }
+}
+
+impl Convert<Complex<i16>> for Complex<f32> {
+    #[inline(always)]
+    fn convert(self) -> Complex<i16> {
+        Complex::new(
+            quantize(self.re, SC16_SCALE, SC16_SCALE - 1.0) as i16,
+            quantize(self.im, SC16_SCALE, SC16_SCALE - 1.0) as i16,
+        )
+    }
+}
+
+impl Convert<Complex<i8>> for Complex<f32> {
+    #[inline(always)]
+    fn convert(self) -> Complex<i8> {
+        Complex::new(
+            quantize(self.re, SC8_SCALE, SC8_SCALE - 1.0) as i8,
+            quantize(self.im, SC8_SCALE, SC8_SCALE - 1.0) as i8,
+        )
+    }
+}
+
+/// Q11 to Q7, rounding to nearest with ties to even
+#[inline(always)]
+fn narrow(v: i16) -> i8 {
+    let q = v >> 4;
+    let r = v & 0xf;
+    let up = (r > 8) | ((r == 8) & (q & 1 == 1));
+    (q + up as i16).clamp(-128, 127) as i8
+}
+
+impl Convert<Complex<i8>> for Complex<i16> {
+    #[inline(always)]
+    fn convert(self) -> Complex<i8> {
+        Complex::new(narrow(self.re), narrow(self.im))
+    }
+}
+
+impl Convert<Complex<i16>> for Complex<i8> {
+    #[inline(always)]
+    fn convert(self) -> Complex<i16> {
+        Complex::new((self.re as i16) << 4, (self.im as i16) << 4)
+    }
+}
+
+/// Convert a buffer of samples
+///
+/// Panics if the buffers differ in length.
+pub fn convert<S, D>(src: &[S], dst: &mut [D])
+where
+    S: Convert<D> + Copy,
+{
+    assert_eq!(src.len(), dst.len(), "buffer lengths differ");
+
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d = s.convert();
+    }
+}
+
+/// View samples as interleaved I/Q components
+pub fn interleaved<T: Sample>(samples: &[T]) -> &[T::Component] {
+    // Safety: Complex is repr(C), laid out as two components
+    unsafe { slice::from_raw_parts(samples.as_ptr() as *const T::Component, samples.len() * 2) }
+}
+
+/// Mutably view samples as interleaved I/Q components
+pub fn interleaved_mut<T: Sample>(samples: &mut [T]) -> &mut [T::Component] {
+    // Safety: as for interleaved
+    unsafe {
+        slice::from_raw_parts_mut(samples.as_mut_ptr() as *mut T::Component, samples.len() * 2)
+    }
+}
+
+/// View interleaved I/Q components as samples, `None` for an odd length
+pub fn from_interleaved<T: Sample>(data: &[T::Component]) -> Option<&[T]> {
+    if data.len() % 2 != 0 {
+        return None;
+    }
+
+    // Safety: samples share the alignment of their components
+    Some(unsafe { slice::from_raw_parts(data.as_ptr() as *const T, data.len() / 2) })
+}
+
+/// Mutably view interleaved I/Q components as samples, `None` for an odd length
+pub fn from_interleaved_mut<T: Sample>(data: &mut [T::Component]) -> Option<&mut [T]> {
+    if data.len() % 2 != 0 {
+        return None;
+    }
+
+    // Safety: as for from_interleaved
+    Some(unsafe { slice::from_raw_parts_mut(data.as_mut_ptr() as *mut T, data.len() / 2) })
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn to_f32<S: Convert<Complex<f32>> + Copy>(s: S) -> Complex<f32> {
+        s.convert()
+    }
+
+    fn c<T: Clone>(v: T) -> Complex<T> {
+        Complex::new(v.clone(), v)
+    }
+
+    #[test]
+    fn test_edges() {
+        let sc16 = |v: f32| -> Complex<i16> { c(v).convert() };
+        assert_eq!(sc16(1.0), c(2047));
+        assert_eq!(sc16(-1.0), c(-2048));
+        assert_eq!(sc16(0.5 / 2048.0), c(0));
+        assert_eq!(sc16(1.5 / 2048.0), c(2));
+        assert_eq!(sc16(-2.5 / 2048.0), c(-2));
+        assert_eq!(sc16(f32::INFINITY), c(2047));
+        assert_eq!(sc16(f32::NEG_INFINITY), c(-2048));
+        assert_eq!(sc16(f32::NAN), c(0));
+
+        let sc8 = |v: f32| -> Complex<i8> { c(v).convert() };
+        assert_eq!(sc8(1.0), c(127));
+        assert_eq!(sc8(-1.0), c(-128));
+        assert_eq!(sc8(f32::NAN), c(0));
+
+        assert_eq!(to_f32(c(-2048i16)), c(-1.0));
+        assert_eq!(to_f32(c(-128i8)), c(-1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "buffer lengths differ")]
+    fn test_length_mismatch() {
+        convert(&[c(0i16); 4], &mut [c(0f32); 3]);
+    }
+
+    #[test]
+    fn test_interleaved() {
+        let mut samples = vec![Complex::new(1i16, -1), Complex::new(2, -2)];
+        assert_eq!(interleaved(&samples), &[1, -1, 2, -2]);
+
+        interleaved_mut(&mut samples)[3] = 5;
+        assert_eq!(samples[1], Complex::new(2, 5));
+
+        let data = [3i8, 4, 5, 6];
+        let view: &[Complex<i8>] = from_interleaved(&data).unwrap();
+        assert_eq!(view, &[Complex::new(3, 4), Complex::new(5, 6)]);
+        assert!(from_interleaved::<Complex<i8>>(&data[..3]).is_none());
+    }
+
+    proptest! {
+        #[test]
+        fn sc16_round_trip(re in -2048i16..=2047, im in -2048i16..=2047) {
+            let s = Complex::new(re, im);
+            let f = to_f32(s);
+            prop_assert_eq!(f, Complex::new(re as f32 / 2048.0, im as f32 / 2048.0));
+            prop_assert!(f.re >= -1.0 && f.re < 1.0);
+
+            let back: Complex<i16> = f.convert();
+            prop_assert_eq!(back, s);
+        }
+
+        #[test]
+        fn sc8_round_trip(re in any::<i8>(), im in any::<i8>()) {
+            let s = Complex::new(re, im);
+            let back: Complex<i8> = to_f32(s).convert();
+            prop_assert_eq!(back, s);
+
+            let wide: Complex<i16> = s.convert();
+            prop_assert_eq!(to_f32(wide), to_f32(s));
+            prop_assert_eq!(Convert::<Complex<i8>>::convert(wide), s);
+        }
+
+        #[test]
+        fn sc16_rounding(v in -1.0f32..1.0) {
+            let q: Complex<i16> = c(v).convert();
+            let err = (q.re as f32 - v * 2048.0).abs();
+            prop_assert!(err <= 0.5 || q.re == 2047, "{} -> {}", v, q.re);
+        }
+
+        #[test]
+        fn saturation(v in any::<f32>()) {
+            let q16: Complex<i16> = c(v).convert();
+            let q8: Complex<i8> = c(v).convert();
+            prop_assert!((-2048..=2047).contains(&q16.re));
+
+            if v.is_nan() {
+                prop_assert_eq!((q16.re, q8.re), (0, 0));
+            } else if v >= 1.0 {
+                prop_assert_eq!((q16.re, q8.re), (2047, 127));
+            } else if v <= -1.0 {
+                prop_assert_eq!((q16.re, q8.re), (-2048, -128));
+            }
+        }
+
+        #[test]
+        fn narrow_matches_float(v in -2048i16..=2047) {
+            let direct: Complex<i8> = c(v).convert();
+            let via: Complex<i8> = to_f32(c(v)).convert();
+            prop_assert_eq!(direct, via);
+        }
+
+        #[test]
+        fn convert_slices(samples in prop::collection::vec(any::<i16>(), 0..64)) {
+            let src: Vec<Complex<i16>> = samples.iter().map(|&v| Complex::new(v, !v)).collect();
+            let mut dst = vec![Complex::new(0f32, 0.0); src.len()];
+            convert(&src, &mut dst);
+
+            for (s, d) in src.iter().zip(&dst) {
+                prop_assert_eq!(to_f32(*s), *d);
+            }
+        }
+    }
+}
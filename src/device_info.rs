@@ -0,0 +1,385 @@
+//! Owned device information for discovery and opening
+
+use std::ffi::{c_char, c_int};
+use std::fmt::Display;
+use std::ptr;
+
+use bladerf_sys::*;
+
+use crate::{BladeRfError, DeviceSelector, ErrorKind};
+
+/// Backend used to communicate with a device
+///
+/// wraps bladerf_backend
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(u32)]
+pub enum Backend {
+    Any = bladerf_backend_BLADERF_BACKEND_ANY,
+    Linux = bladerf_backend_BLADERF_BACKEND_LINUX,
+    Libusb = bladerf_backend_BLADERF_BACKEND_LIBUSB,
+    Cypress = bladerf_backend_BLADERF_BACKEND_CYPRESS,
+    Dummy = bladerf_backend_BLADERF_BACKEND_DUMMY,
+}
+
+impl Backend {
+    /// Name of the backend as used in device identifier strings
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Any => "*",
+            Self::Linux => "linux",
+            Self::Libusb => "libusb",
+            Self::Cypress => "cypress",
+            Self::Dummy => "dummy",
+        }
+    }
+}
+
+impl TryFrom<bladerf_backend> for Backend {
+    type Error = bladerf_backend;
+
+    fn try_from(value: bladerf_backend) -> Result<Self, bladerf_backend> {
+        let v = match value {
+            bladerf_backend_BLADERF_BACKEND_ANY => Self::Any,
+            bladerf_backend_BLADERF_BACKEND_LINUX => Self::Linux,
+            bladerf_backend_BLADERF_BACKEND_LIBUSB => Self::Libusb,
+            bladerf_backend_BLADERF_BACKEND_CYPRESS => Self::Cypress,
+            bladerf_backend_BLADERF_BACKEND_DUMMY => Self::Dummy,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Information about an attached device
+///
+/// Owned equivalent of bladerf_devinfo
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceInfo {
+    pub backend: Backend,
+    pub serial: String,
+    pub usb_bus: u8,
+    pub usb_addr: u8,
+    pub instance: u32,
+    pub manufacturer: String,
+    pub product: String,
+}
+
+impl DeviceInfo {
+    /// Fixture shared by tests
+    #[cfg(test)]
+    pub(crate) fn example() -> Self {
+        Self {
+            backend: Backend::Libusb,
+            serial: "a66ab3dcbf174d2d97b3f4e8fa4e4a28".to_string(),
+            usb_bus: 2,
+            usb_addr: 5,
+            instance: 0,
+            manufacturer: "Nuand".to_string(),
+            product: "bladeRF 2.0".to_string(),
+        }
+    }
+
+    /// Build the identifier string accepted by [`BladeRF::open`](crate::BladeRF::open)
+    pub fn identifier(&self) -> String {
+        DeviceSelector::from(self).to_string()
+    }
+
+    /// Convert back into a raw bladerf_devinfo
+    pub fn to_devinfo(&self) -> bladerf_devinfo {
+        let mut devinfo = bladerf_devinfo {
+            backend: self.backend as bladerf_backend,
+            serial: [0; BLADERF_SERIAL_LENGTH as usize],
+            usb_bus: self.usb_bus,
+            usb_addr: self.usb_addr,
+            instance: self.instance,
+            manufacturer: [0; BLADERF_DESCRIPTION_LENGTH as usize],
+            product: [0; BLADERF_DESCRIPTION_LENGTH as usize],
+        };
+
+        string_to_chars(&self.serial, &mut devinfo.serial);
+        string_to_chars(&self.manufacturer, &mut devinfo.manufacturer);
+        string_to_chars(&self.product, &mut devinfo.product);
+
+        devinfo
+    }
+}
+
+impl From<&bladerf_devinfo> for DeviceInfo {
+    fn from(value: &bladerf_devinfo) -> Self {
+        Self {
+            // libbladeRF only reports the backends listed above
+            backend: Backend::try_from(value.backend).unwrap_or(Backend::Any),
+            serial: chars_to_string(&value.serial),
+            usb_bus: value.usb_bus,
+            usb_addr: value.usb_addr,
+            instance: value.instance,
+            manufacturer: chars_to_string(&value.manufacturer),
+            product: chars_to_string(&value.product),
+        }
+    }
+}
+
+impl From<&DeviceInfo> for bladerf_devinfo {
+    fn from(value: &DeviceInfo) -> Self {
+        value.to_devinfo()
+    }
+}
+
+impl Display for DeviceInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} (serial: {}, backend: {}, bus: {}, address: {}, instance: {})",
+            self.manufacturer,
+            self.product,
+            self.serial,
+            self.backend,
+            self.usb_bus,
+            self.usb_addr,
+            self.instance
+        )
+    }
+}
+
+/// Source of raw device lists
+///
+/// Abstracts bladerf_get_device_list / bladerf_free_device_list so that
+/// enumeration can be exercised without hardware attached.
+pub(crate) trait DeviceListProvider {
+    /// Populate `devices` with a list, returning the number of entries or an error code
+    ///
+    /// # Safety
+    /// Same contract as bladerf_get_device_list
+    unsafe fn get_device_list(&self, devices: *mut *mut bladerf_devinfo) -> c_int;
+
+    /// Free a list returned by [`DeviceListProvider::get_device_list`]
+    ///
+    /// # Safety
+    /// Same contract as bladerf_free_device_list
+    unsafe fn free_device_list(&self, devices: *mut bladerf_devinfo);
+}
+
+/// Device lists provided by libbladeRF
+pub(crate) struct LibBladeRf;
+
+impl DeviceListProvider for LibBladeRf {
+    unsafe fn get_device_list(&self, devices: *mut *mut bladerf_devinfo) -> c_int {
+        bladerf_get_device_list(devices)
+    }
+
+    unsafe fn free_device_list(&self, devices: *mut bladerf_devinfo) {
+        bladerf_free_device_list(devices)
+    }
+}
+
+/// Fetch and copy the device list from a provider, keeping devices using `backend`
+pub(crate) fn device_list<P: DeviceListProvider>(
+    provider: &P,
+    backend: Backend,
+) -> Result<Vec<DeviceInfo>, BladeRfError> {
+    let mut devices: *mut bladerf_devinfo = ptr::null_mut();
+
+    // Safety: libbladeRF allocates a contiguous array of n devinfo structures
+    // and assigns it to devices when n > 0, otherwise it is left untouched.
+    // https://github.com/Nuand/bladeRF/blob/fe3304d75967c88ab4f17ff37cb5daf8ff53d3e1/host/libraries/libbladeRF/src/devinfo.c#L58
+    let n = unsafe { provider.get_device_list(&mut devices) };
+
+    // No devices is reported as an error by libbladeRF, but is not one for us
+    if n == i32::from(ErrorKind::Nodev) || (n == 0 && devices.is_null()) {
+        return Ok(Vec::new());
+    }
+    if n < 0 {
+        return Err(BladeRfError::from_code(n, "bladerf_get_device_list"));
+    }
+    if devices.is_null() {
+        return Err(BladeRfError::new(
+            ErrorKind::Unexpected,
+            "bladerf_get_device_list",
+        ));
+    }
+
+    // Safety: devices points to n initialised entries, which are copied into
+    // owned objects before the list is freed exactly once.
+    let device_list = unsafe {
+        let device_slice = std::slice::from_raw_parts(devices, n as usize);
+        let device_list = device_slice
+            .iter()
+            .map(DeviceInfo::from)
+            .filter(|d| backend == Backend::Any || d.backend == backend)
+            .collect();
+        provider.free_device_list(devices);
+        device_list
+    };
+
+    Ok(device_list)
+}
+
+/// Read a null terminated (or full length) C char array into a String
+fn chars_to_string(chars: &[c_char]) -> String {
+    let bytes: Vec<u8> = chars
+        .iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| c as u8)
+        .collect();
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Write a String into a C char array, truncating to leave a null terminator
+fn string_to_chars(s: &str, chars: &mut [c_char]) {
+    let max = chars.len() - 1;
+    for (c, b) in chars.iter_mut().zip(s.bytes().take(max)) {
+        *c = b as c_char;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+
+    use super::*;
+
+    /// Fake provider that allocates lists the same way libbladeRF does
+    struct FakeProvider {
+        result: c_int,
+        devices: Vec<DeviceInfo>,
+        allocated: RefCell<Option<(*mut bladerf_devinfo, usize)>>,
+        frees: Cell<usize>,
+    }
+
+    impl FakeProvider {
+        fn new(devices: Vec<DeviceInfo>) -> Self {
+            Self {
+                result: devices.len() as c_int,
+                devices,
+                allocated: RefCell::new(None),
+                frees: Cell::new(0),
+            }
+        }
+
+        fn error(code: c_int) -> Self {
+            Self {
+                result: code,
+                ..Self::new(vec![])
+            }
+        }
+    }
+
+    impl DeviceListProvider for FakeProvider {
+        unsafe fn get_device_list(&self, devices: *mut *mut bladerf_devinfo) -> c_int {
+            if self.result > 0 {
+                let list: Box<[bladerf_devinfo]> =
+                    self.devices.iter().map(DeviceInfo::to_devinfo).collect();
+                let len = list.len();
+                let p = Box::into_raw(list) as *mut bladerf_devinfo;
+
+                *self.allocated.borrow_mut() = Some((p, len));
+                *devices = p;
+            }
+            self.result
+        }
+
+        unsafe fn free_device_list(&self, devices: *mut bladerf_devinfo) {
+            let (p, len) = self.allocated.borrow_mut().take().expect("double free");
+            assert_eq!(p, devices);
+
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(p, len)));
+            self.frees.set(self.frees.get() + 1);
+        }
+    }
+
+    fn rack() -> Vec<DeviceInfo> {
+        (0..4)
+            .map(|i| DeviceInfo {
+                serial: format!("{:032x}", i + 1),
+                usb_addr: 5 + i as u8,
+                instance: i,
+                ..DeviceInfo::example()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_device_list_copies_every_entry() {
+        let provider = FakeProvider::new(rack());
+
+        let devices = device_list(&provider, Backend::Any).unwrap();
+
+        assert_eq!(devices, rack());
+        assert_eq!(provider.frees.get(), 1);
+    }
+
+    #[test]
+    fn test_device_list_by_backend() {
+        let mut devices = rack();
+        devices[1].backend = Backend::Cypress;
+        devices[3].backend = Backend::Dummy;
+        let provider = FakeProvider::new(devices.clone());
+
+        let libusb = device_list(&provider, Backend::Libusb).unwrap();
+        assert_eq!(libusb, vec![devices[0].clone(), devices[2].clone()]);
+
+        let dummy = device_list(&provider, Backend::Dummy).unwrap();
+        assert_eq!(dummy, vec![devices[3].clone()]);
+
+        assert_eq!(provider.frees.get(), 2);
+    }
+
+    #[test]
+    fn test_device_list_empty() {
+        let provider = FakeProvider::error(ErrorKind::Nodev.into());
+
+        let devices = device_list(&provider, Backend::Any).unwrap();
+
+        assert!(devices.is_empty());
+        assert_eq!(provider.frees.get(), 0);
+    }
+
+    #[test]
+    fn test_device_list_error() {
+        let provider = FakeProvider::error(ErrorKind::Mem.into());
+
+        let err = device_list(&provider, Backend::Any).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Mem);
+        assert_eq!(provider.frees.get(), 0);
+    }
+
+    #[test]
+    fn test_devinfo_round_trip() {
+        let info = DeviceInfo::example();
+
+        let raw = info.to_devinfo();
+        assert_eq!(raw.serial(), info.serial);
+        assert_eq!(DeviceInfo::from(&raw), info);
+    }
+
+    #[test]
+    fn test_identifier() {
+        assert_eq!(
+            DeviceInfo::example().identifier(),
+            "libusb:device=2:5 instance=0 serial=a66ab3dcbf174d2d97b3f4e8fa4e4a28"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let info = DeviceInfo::example();
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["backend"], "libusb");
+
+        let parsed: DeviceInfo = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, info);
+    }
+}
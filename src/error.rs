@@ -2,10 +2,12 @@ use std::{error::Error, ffi::CStr, fmt::Display};
 
 use bladerf_sys as sys;
 
+use crate::Channel;
+
 /// Error Codes as defined in <https://nuand.com/libbladeRF-doc/v2.5.0/group___r_e_t_c_o_d_e_s.html>
 #[repr(i32)]
-#[derive(Debug, Clone, Copy)]
-pub enum BladeRfError {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
     Unexpected = -1,
     Range = -2,
     Inval = -3,
@@ -29,7 +31,7 @@ pub enum BladeRfError {
     Unknown(i32) = i32::MIN,
 }
 
-impl BladeRfError {
+impl ErrorKind {
     pub fn from_code(code: i32) -> Self {
         match code {
             -1 => Self::Unexpected,
@@ -54,47 +56,136 @@ impl BladeRfError {
             x => Self::Unknown(x),
         }
     }
+
+    /// Fetch the libbladeRF description of this error
+    pub fn message(&self) -> &'static str {
+        // Safety: the function https://github.com/Nuand/bladeRF/blob/fe3304d75967c88ab4f17ff37cb5daf8ff53d3e1/host/libraries/libbladeRF/src/bladerf.c#L1784
+        // Returns a valid, static Cstring for any i32 input.
+        let msg_ptr = unsafe { sys::bladerf_strerror(i32::from(*self)) };
+        unsafe {
+            CStr::from_ptr(msg_ptr)
+                .to_str()
+                .expect("These strings all seems to be valid ascii and thus UTF8")
+        }
+    }
 }
 
-impl From<BladeRfError> for i32 {
-    fn from(value: BladeRfError) -> Self {
+impl From<ErrorKind> for i32 {
+    fn from(value: ErrorKind) -> Self {
         match value {
-            BladeRfError::Unexpected => -1,
-            BladeRfError::Range => -2,
-            BladeRfError::Inval => -3,
-            BladeRfError::Mem => -4,
-            BladeRfError::Io => -5,
-            BladeRfError::Timeout => -6,
-            BladeRfError::Nodev => -7,
-            BladeRfError::Unsupported => -8,
-            BladeRfError::Misaligned => -9,
-            BladeRfError::Checksum => -10,
-            BladeRfError::NoFile => -11,
-            BladeRfError::UpdateFpga => -12,
-            BladeRfError::UpdateFw => -13,
-            BladeRfError::TimePast => -14,
-            BladeRfError::QueueFull => -15,
-            BladeRfError::FpgaOp => -16,
-            BladeRfError::Permission => -17,
-            BladeRfError::WouldBlock => -18,
-            BladeRfError::NotInit => -19,
-            BladeRfError::Unknown(x) => x,
+            ErrorKind::Unexpected => -1,
+            ErrorKind::Range => -2,
+            ErrorKind::Inval => -3,
+            ErrorKind::Mem => -4,
+            ErrorKind::Io => -5,
+            ErrorKind::Timeout => -6,
+            ErrorKind::Nodev => -7,
+            ErrorKind::Unsupported => -8,
+            ErrorKind::Misaligned => -9,
+            ErrorKind::Checksum => -10,
+            ErrorKind::NoFile => -11,
+            ErrorKind::UpdateFpga => -12,
+            ErrorKind::UpdateFw => -13,
+            ErrorKind::TimePast => -14,
+            ErrorKind::QueueFull => -15,
+            ErrorKind::FpgaOp => -16,
+            ErrorKind::Permission => -17,
+            ErrorKind::WouldBlock => -18,
+            ErrorKind::NotInit => -19,
+            ErrorKind::Unknown(x) => x,
         }
     }
 }
 
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+/// Error returned by [`BladeRF`](crate::BladeRF) methods
+///
+/// Wraps the libbladeRF error code along with the operation
+/// (and channel where applicable) that produced it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BladeRfError {
+    kind: ErrorKind,
+    op: &'static str,
+    channel: Option<Channel>,
+    detail: Option<String>,
+}
+
+impl BladeRfError {
+    /// Create an error from a libbladeRF return code
+    pub fn from_code(code: i32, op: &'static str) -> Self {
+        Self::new(ErrorKind::from_code(code), op)
+    }
+
+    /// Create an error of the provided kind
+    pub fn new(kind: ErrorKind, op: &'static str) -> Self {
+        Self {
+            kind,
+            op,
+            channel: None,
+            detail: None,
+        }
+    }
+
+    /// Attach the channel the failing operation was addressing
+    pub fn with_channel(mut self, channel: Channel) -> Self {
+        self.channel = Some(channel);
+        self
+    }
+
+    /// Attach a description of what went wrong
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Fetch the error kind, for matching on specific failures
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Fetch the raw libbladeRF error code
+    pub fn code(&self) -> i32 {
+        i32::from(self.kind)
+    }
+
+    /// Fetch the name of the operation that failed
+    pub fn op(&self) -> &'static str {
+        self.op
+    }
+
+    /// Fetch the channel the failing operation was addressing, if any
+    pub fn channel(&self) -> Option<Channel> {
+        self.channel
+    }
+
+    /// Fetch the description of what went wrong, if any
+    pub fn detail(&self) -> Option<&str> {
+        self.detail.as_deref()
+    }
+}
+
+impl From<BladeRfError> for ErrorKind {
+    fn from(value: BladeRfError) -> Self {
+        value.kind
+    }
+}
+
 impl Display for BladeRfError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let disciminant = i32::from(*self);
-        // Safety: the function https://github.com/Nuand/bladeRF/blob/fe3304d75967c88ab4f17ff37cb5daf8ff53d3e1/host/libraries/libbladeRF/src/bladerf.c#L1784
-        // Returns a valid Cstring for any i32 input.
-        let msg_ptr = unsafe { sys::bladerf_strerror(disciminant) };
-        let msg = unsafe {
-            CStr::from_ptr(msg_ptr)
-                .to_str()
-                .expect("These strings all seems to be valid ascii and thus UTF8")
-        };
-        write!(f, "BladeRF Error: {}", msg)
+        write!(f, "BladeRF Error: {}", self.op)?;
+        if let Some(ch) = self.channel {
+            write!(f, " (channel {})", ch)?;
+        }
+        write!(f, ": {}", self.kind)?;
+        if let Some(detail) = &self.detail {
+            write!(f, " ({})", detail)?;
+        }
+        Ok(())
     }
 }
 
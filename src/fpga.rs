@@ -0,0 +1,217 @@
+//! FPGA bitstream discovery
+//!
+//! Bitstreams are located by name for the detected [`FpgaSize`], searching
+//! environment overrides, the working directory, then the usual install paths.
+
+use std::env;
+use std::ffi::CString;
+use std::path::{Path, PathBuf};
+
+use crate::{BladeRfError, ErrorKind, FpgaSize};
+
+/// Environment variable naming an explicit bitstream file, bypassing the search
+pub const FPGA_ENV: &str = "BLADERF_FPGA";
+
+/// Environment variable listing directories to search (as used by libbladeRF)
+pub const SEARCH_DIR_ENV: &str = "BLADERF_SEARCH_DIR";
+
+/// Standard install locations for bitstreams
+const INSTALL_DIRS: [&str; 3] = [
+    "/etc/Nuand/bladeRF",
+    "/usr/local/share/Nuand/bladeRF",
+    "/usr/share/Nuand/bladeRF",
+];
+
+impl FpgaSize {
+    /// File name of the hosted bitstream for this FPGA
+    pub fn bitstream_name(&self) -> Option<&'static str> {
+        match self {
+            Self::Unknown => None,
+            Self::Kle40 => Some("hostedx40.rbf"),
+            Self::Kle115 => Some("hostedx115.rbf"),
+            Self::A4 => Some("hostedxA4.rbf"),
+            Self::A5 => Some("hostedxA5.rbf"),
+            Self::A9 => Some("hostedxA9.rbf"),
+        }
+    }
+}
+
+/// Convert a bitstream path for libbladeRF, keeping non-UTF-8 paths intact on unix
+pub(crate) fn path_cstring(path: &Path, op: &'static str) -> Result<CString, BladeRfError> {
+    #[cfg(unix)]
+    let bytes = Some(std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()));
+    #[cfg(not(unix))]
+    let bytes = path.to_str().map(str::as_bytes);
+
+    let invalid = |detail: &str| {
+        BladeRfError::new(ErrorKind::Inval, op).with_detail(format!(
+            "{} '{}'",
+            detail,
+            path.display()
+        ))
+    };
+    let bytes = bytes.ok_or_else(|| invalid("path is not valid UTF-8"))?;
+    CString::new(bytes).map_err(|_| invalid("path contains a NUL byte"))
+}
+
+/// Configurable search for FPGA bitstreams
+///
+/// The default search checks [`FPGA_ENV`], directories in [`SEARCH_DIR_ENV`],
+/// the working directory, `~/.config/Nuand/bladeRF` and the standard install paths.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FpgaSearch {
+    paths: Vec<PathBuf>,
+    env: bool,
+}
+
+impl Default for FpgaSearch {
+    fn default() -> Self {
+        let mut paths = vec![PathBuf::from(".")];
+
+        if let Some(home) = env::var_os("HOME") {
+            paths.push(Path::new(&home).join(".config/Nuand/bladeRF"));
+        }
+
+        paths.extend(INSTALL_DIRS.iter().map(PathBuf::from));
+
+        Self { paths, env: true }
+    }
+}
+
+impl FpgaSearch {
+    /// Create an empty search, ignoring environment variables
+    pub fn new() -> Self {
+        Self {
+            paths: vec![],
+            env: false,
+        }
+    }
+
+    /// Append a directory to the search
+    pub fn path(mut self, dir: impl AsRef<Path>) -> Self {
+        self.paths.push(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Enable or disable environment variable overrides
+    pub fn env(mut self, enabled: bool) -> Self {
+        self.env = enabled;
+        self
+    }
+
+    /// Directories searched, in order
+    pub fn search_paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![];
+
+        if self.env {
+            if let Some(dirs) = env::var_os(SEARCH_DIR_ENV) {
+                paths.extend(env::split_paths(&dirs));
+            }
+        }
+
+        paths.extend(self.paths.iter().cloned());
+        paths
+    }
+
+    /// Locate the bitstream for the provided FPGA size
+    pub fn find(&self, size: FpgaSize) -> Result<PathBuf, BladeRfError> {
+        if self.env {
+            if let Some(file) = env::var_os(FPGA_ENV) {
+                let file = PathBuf::from(file);
+                if file.is_file() {
+                    return Ok(file);
+                }
+
+                return Err(BladeRfError::new(ErrorKind::NoFile, "FpgaSearch::find")
+                    .with_detail(format!("{} '{}' not found", FPGA_ENV, file.display())));
+            }
+        }
+
+        let name = size.bitstream_name().ok_or_else(|| {
+            BladeRfError::new(ErrorKind::Unsupported, "FpgaSearch::find")
+                .with_detail("unknown FPGA size")
+        })?;
+
+        let paths = self.search_paths();
+        if let Some(file) = paths.iter().map(|d| d.join(name)).find(|f| f.is_file()) {
+            return Ok(file);
+        }
+
+        let searched: Vec<_> = paths.iter().map(|p| p.display().to_string()).collect();
+        Err(
+            BladeRfError::new(ErrorKind::NoFile, "FpgaSearch::find").with_detail(format!(
+                "{} not found in [{}]",
+                name,
+                searched.join(", ")
+            )),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("bladerf-fpga-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_find() {
+        let empty = temp_dir("empty");
+        let dir = temp_dir("find");
+        fs::write(dir.join("hostedxA4.rbf"), b"").unwrap();
+
+        let search = FpgaSearch::new().path(&empty).path(&dir);
+        assert_eq!(
+            search.find(FpgaSize::A4).unwrap(),
+            dir.join("hostedxA4.rbf")
+        );
+
+        let err = search.find(FpgaSize::A9).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NoFile);
+        assert!(err.detail().unwrap().contains("hostedxA9.rbf"));
+
+        let err = search.find(FpgaSize::Unknown).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+
+        fs::remove_dir_all(empty).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_search_paths() {
+        let search = FpgaSearch::new().path("/a").path("/b");
+        assert_eq!(
+            search.search_paths(),
+            vec![PathBuf::from("/a"), PathBuf::from("/b")]
+        );
+
+        let default = FpgaSearch::default();
+        assert_eq!(default.paths[0], PathBuf::from("."));
+        assert!(default
+            .paths
+            .contains(&PathBuf::from("/usr/share/Nuand/bladeRF")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_path_cstring() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"/tmp/hosted\xff.rbf"));
+        let c = path_cstring(path, "op").unwrap();
+        assert_eq!(c.as_bytes(), b"/tmp/hosted\xff.rbf");
+
+        let nul = Path::new(OsStr::from_bytes(b"/tmp/a\0b"));
+        assert_eq!(
+            path_cstring(nul, "op").unwrap_err().kind(),
+            ErrorKind::Inval
+        );
+    }
+}
@@ -3,7 +3,6 @@
 //!
 #![allow(non_upper_case_globals)]
 
-use std::mem::*;
 use std::*;
 
 use cmp::Ordering;
@@ -11,61 +10,131 @@ use num_complex::Complex;
 
 use bladerf_sys::*;
 
+use config::GainTarget;
+use stream::DeviceHandle;
+
+pub mod channel;
+pub use channel::{Channel, ChannelLayout, Direction};
+
 pub mod error;
+pub use error::{BladeRfError, ErrorKind};
+
+pub mod device_info;
+pub use device_info::{Backend, DeviceInfo};
+
+pub mod selector;
+pub use selector::DeviceSelector;
+
+pub mod watcher;
+pub use watcher::{DeviceEvent, DeviceTracker, DeviceWatcher};
+
+pub mod board;
+pub use board::{Board, Capabilities};
+
+pub mod version;
+pub use version::{MinVersions, Version};
+
+pub mod range;
+pub use range::Range;
+
+pub mod units;
+pub use units::{Bandwidth, Decibels, Hertz, RationalRate, SampleRate};
+
+pub mod config;
+pub use config::{BladeRFConfig, BladeRFModuleConfig, ConfigError, Corrections, Setting};
+
+pub mod report;
+pub use report::DeviceReport;
+
+pub mod fpga;
+pub use fpga::FpgaSearch;
 
-// Macro to simplify integer returns
+pub mod sample;
+pub use sample::Sample;
+
+pub mod metadata;
+pub use metadata::{MetaFlags, MetaStatus, Metadata};
+
+pub mod convert;
+pub use convert::Convert;
+
+pub mod stream;
+pub use stream::{RxStream, StreamConfig, TxStream};
+
+pub mod async_stream;
+pub use async_stream::{AsyncStream, StreamAction};
+
+#[cfg(feature = "async")]
+pub mod sample_stream;
+#[cfg(feature = "async")]
+pub use sample_stream::{RxSampleStream, SampleBlock, TxSampleSink};
+
+pub mod open_options;
+pub use open_options::OpenOptions;
+
+// Macro to map libbladeRF return codes to results
 macro_rules! handle_res {
-    ($e:expr) => (
-    	if $e >= 0 {
-			return Ok($e as isize)
-		} else {
-			return Err($e as isize)
-		}
-	);
-	($res:expr, $out:expr) => (
-		if $res >= 0 {
-			return Ok($out)
-		} else {
-			return Err($res as isize)
-		}
-	);
-}
-
-// BladeRF module config object
-#[derive(Clone, Debug)]
-pub struct BladeRFModuleConfig {
-    pub frequency: u64,
-    pub sample_rate: u32,
-    pub bandwidth: u32,
-    pub lna_gain: i32,
-    pub vga1: i32,
-    pub vga2: i32,
-}
-
-// BladeRF overall config object
-pub struct BladeRFConfig {
-    pub tx: BladeRFModuleConfig,
-    pub rx: BladeRFModuleConfig,
+    ($res:expr, $op:expr, channel = $ch:expr) => {
+        handle_res!($res, $op, channel = $ch, ())
+    };
+    ($res:expr, $op:expr, channel = $ch:expr, $out:expr) => {
+        if $res >= 0 {
+            return Ok($out);
+        } else {
+            return Err(BladeRfError::from_code($res, $op).with_channel($ch));
+        }
+    };
+    ($res:expr, $op:expr) => {
+        handle_res!($res, $op, ())
+    };
+    ($res:expr, $op:expr, $out:expr) => {
+        if $res >= 0 {
+            return Ok($out);
+        } else {
+            return Err(BladeRfError::from_code($res, $op));
+        }
+    };
 }
 
 // BladeRF device object
 pub struct BladeRF {
     device: *mut bladerf,
+    validate: sync::atomic::AtomicBool,
+    /// Active stream guards, indexed by direction
+    streams: [sync::atomic::AtomicBool; 2],
+    /// Sync interface formats, indexed by direction
+    sync_formats: [sync::Mutex<SyncFormat>; 2],
+    /// Running asynchronous streams, stopped before the device is closed
+    async_streams: sync::Mutex<Vec<sync::Arc<async_stream::Shared>>>,
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
-#[repr(u32)]
-pub enum BladeRFChannel {
-    Rx1 = bladerf_channel_layout_BLADERF_RX_X1,
-    Rx2 = bladerf_channel_layout_BLADERF_RX_X2,
-    Tx1 = bladerf_channel_layout_BLADERF_TX_X1,
-    Tx2 = bladerf_channel_layout_BLADERF_TX_X2,
+/// Format of the sync interface in one direction
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+enum SyncFormat {
+    /// `sync_config` has not been called
+    #[default]
+    Unconfigured,
+    Configured(Format),
+    /// A reconfiguration failed, leaving the format in use unknown
+    Unknown,
 }
 
+// Safety: libbladeRF serialises control calls on a device handle with its
+// per-device lock, but the sync interface only has per-direction locks. Here
+// each sync transfer and sync_config holds the direction's format mutex across
+// the libbladeRF call, and sync_config is refused while a stream is active in
+// the direction, so a transfer's buffer always matches the format in use. The
+// remaining state is atomic or behind a mutex, and the device is only closed
+// on drop, once no other reference exists.
+unsafe impl Send for BladeRF {}
+unsafe impl Sync for BladeRF {}
+
 /// Loopback configuration
 ///
 /// wraps bladerf_loopback
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 #[repr(u32)]
 pub enum BladeRFLoopback {
     None = bladerf_loopback_BLADERF_LB_NONE,
@@ -102,8 +171,428 @@ impl TryFrom<bladerf_loopback> for BladeRFLoopback {
     }
 }
 
+/// Gain control mode
+///
+/// wraps bladerf_gain_mode
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(u32)]
+pub enum GainMode {
+    Default = bladerf_gain_mode_BLADERF_GAIN_DEFAULT,
+    Manual = bladerf_gain_mode_BLADERF_GAIN_MGC,
+    FastAttackAgc = bladerf_gain_mode_BLADERF_GAIN_FASTATTACK_AGC,
+    SlowAttackAgc = bladerf_gain_mode_BLADERF_GAIN_SLOWATTACK_AGC,
+    HybridAgc = bladerf_gain_mode_BLADERF_GAIN_HYBRID_AGC,
+}
+
+impl TryFrom<bladerf_gain_mode> for GainMode {
+    type Error = bladerf_gain_mode;
+
+    fn try_from(value: bladerf_gain_mode) -> Result<Self, bladerf_gain_mode> {
+        let v = match value {
+            bladerf_gain_mode_BLADERF_GAIN_DEFAULT => Self::Default,
+            bladerf_gain_mode_BLADERF_GAIN_MGC => Self::Manual,
+            bladerf_gain_mode_BLADERF_GAIN_FASTATTACK_AGC => Self::FastAttackAgc,
+            bladerf_gain_mode_BLADERF_GAIN_SLOWATTACK_AGC => Self::SlowAttackAgc,
+            bladerf_gain_mode_BLADERF_GAIN_HYBRID_AGC => Self::HybridAgc,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
+/// USB connection speed
+///
+/// wraps bladerf_dev_speed
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(u32)]
+pub enum UsbSpeed {
+    Unknown = bladerf_dev_speed_BLADERF_DEVICE_SPEED_UNKNOWN,
+    High = bladerf_dev_speed_BLADERF_DEVICE_SPEED_HIGH,
+    Super = bladerf_dev_speed_BLADERF_DEVICE_SPEED_SUPER,
+}
+
+impl TryFrom<bladerf_dev_speed> for UsbSpeed {
+    type Error = bladerf_dev_speed;
+
+    fn try_from(value: bladerf_dev_speed) -> Result<Self, bladerf_dev_speed> {
+        let v = match value {
+            bladerf_dev_speed_BLADERF_DEVICE_SPEED_UNKNOWN => Self::Unknown,
+            bladerf_dev_speed_BLADERF_DEVICE_SPEED_HIGH => Self::High,
+            bladerf_dev_speed_BLADERF_DEVICE_SPEED_SUPER => Self::Super,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
+/// FPGA device variant
+///
+/// wraps bladerf_fpga_size
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(u32)]
+pub enum FpgaSize {
+    Unknown = bladerf_fpga_size_BLADERF_FPGA_UNKNOWN,
+    /// 40 kLE (bladeRF x40)
+    Kle40 = bladerf_fpga_size_BLADERF_FPGA_40KLE,
+    /// 115 kLE (bladeRF x115)
+    Kle115 = bladerf_fpga_size_BLADERF_FPGA_115KLE,
+    /// 49 kLE (bladeRF 2.0 micro xA4)
+    A4 = bladerf_fpga_size_BLADERF_FPGA_A4,
+    /// 77 kLE (bladeRF 2.0 micro xA5)
+    A5 = bladerf_fpga_size_BLADERF_FPGA_A5,
+    /// 301 kLE (bladeRF 2.0 micro xA9)
+    A9 = bladerf_fpga_size_BLADERF_FPGA_A9,
+}
+
+impl TryFrom<bladerf_fpga_size> for FpgaSize {
+    type Error = bladerf_fpga_size;
+
+    fn try_from(value: bladerf_fpga_size) -> Result<Self, bladerf_fpga_size> {
+        let v = match value {
+            bladerf_fpga_size_BLADERF_FPGA_UNKNOWN => Self::Unknown,
+            bladerf_fpga_size_BLADERF_FPGA_40KLE => Self::Kle40,
+            bladerf_fpga_size_BLADERF_FPGA_115KLE => Self::Kle115,
+            bladerf_fpga_size_BLADERF_FPGA_A4 => Self::A4,
+            bladerf_fpga_size_BLADERF_FPGA_A5 => Self::A5,
+            bladerf_fpga_size_BLADERF_FPGA_A9 => Self::A9,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
+/// Source the FPGA was configured from
+///
+/// wraps bladerf_fpga_source
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(u32)]
+pub enum FpgaSource {
+    Unknown = bladerf_fpga_source_BLADERF_FPGA_SOURCE_UNKNOWN,
+    Flash = bladerf_fpga_source_BLADERF_FPGA_SOURCE_FLASH,
+    Host = bladerf_fpga_source_BLADERF_FPGA_SOURCE_HOST,
+}
+
+impl TryFrom<bladerf_fpga_source> for FpgaSource {
+    type Error = bladerf_fpga_source;
+
+    fn try_from(value: bladerf_fpga_source) -> Result<Self, bladerf_fpga_source> {
+        let v = match value {
+            bladerf_fpga_source_BLADERF_FPGA_SOURCE_UNKNOWN => Self::Unknown,
+            bladerf_fpga_source_BLADERF_FPGA_SOURCE_FLASH => Self::Flash,
+            bladerf_fpga_source_BLADERF_FPGA_SOURCE_HOST => Self::Host,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
+/// ADC sampling source (bladeRF 1 only)
+///
+/// wraps bladerf_sampling
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(u32)]
+pub enum Sampling {
+    Unknown = bladerf_sampling_BLADERF_SAMPLING_UNKNOWN,
+    /// Sample from the RX / TX connectors
+    Internal = bladerf_sampling_BLADERF_SAMPLING_INTERNAL,
+    /// Sample from the J60 / J61 expansion connectors
+    External = bladerf_sampling_BLADERF_SAMPLING_EXTERNAL,
+}
+
+impl TryFrom<bladerf_sampling> for Sampling {
+    type Error = bladerf_sampling;
+
+    fn try_from(value: bladerf_sampling) -> Result<Self, bladerf_sampling> {
+        let v = match value {
+            bladerf_sampling_BLADERF_SAMPLING_UNKNOWN => Self::Unknown,
+            bladerf_sampling_BLADERF_SAMPLING_INTERNAL => Self::Internal,
+            bladerf_sampling_BLADERF_SAMPLING_EXTERNAL => Self::External,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
+/// Source of RX samples
+///
+/// wraps bladerf_rx_mux
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(i32)]
+pub enum RxMux {
+    /// Samples from the RFIC / LMS
+    Baseband = bladerf_rx_mux_BLADERF_RX_MUX_BASEBAND,
+    /// 12-bit counter test pattern
+    Counter12Bit = bladerf_rx_mux_BLADERF_RX_MUX_12BIT_COUNTER,
+    /// 32-bit counter test pattern
+    Counter32Bit = bladerf_rx_mux_BLADERF_RX_MUX_32BIT_COUNTER,
+    /// TX samples looped back in the FPGA
+    DigitalLoopback = bladerf_rx_mux_BLADERF_RX_MUX_DIGITAL_LOOPBACK,
+}
+
+impl TryFrom<bladerf_rx_mux> for RxMux {
+    type Error = bladerf_rx_mux;
+
+    fn try_from(value: bladerf_rx_mux) -> Result<Self, bladerf_rx_mux> {
+        let v = match value {
+            bladerf_rx_mux_BLADERF_RX_MUX_BASEBAND => Self::Baseband,
+            bladerf_rx_mux_BLADERF_RX_MUX_12BIT_COUNTER => Self::Counter12Bit,
+            bladerf_rx_mux_BLADERF_RX_MUX_32BIT_COUNTER => Self::Counter32Bit,
+            bladerf_rx_mux_BLADERF_RX_MUX_DIGITAL_LOOPBACK => Self::DigitalLoopback,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
+/// Low pass filter mode (bladeRF 1 only)
+///
+/// wraps bladerf_lpf_mode
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(u32)]
+pub enum LpfMode {
+    Normal = bladerf_lpf_mode_BLADERF_LPF_NORMAL,
+    Bypassed = bladerf_lpf_mode_BLADERF_LPF_BYPASSED,
+    Disabled = bladerf_lpf_mode_BLADERF_LPF_DISABLED,
+}
+
+impl TryFrom<bladerf_lpf_mode> for LpfMode {
+    type Error = bladerf_lpf_mode;
+
+    fn try_from(value: bladerf_lpf_mode) -> Result<Self, bladerf_lpf_mode> {
+        let v = match value {
+            bladerf_lpf_mode_BLADERF_LPF_NORMAL => Self::Normal,
+            bladerf_lpf_mode_BLADERF_LPF_BYPASSED => Self::Bypassed,
+            bladerf_lpf_mode_BLADERF_LPF_DISABLED => Self::Disabled,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
+/// Frequency tuning mode
+///
+/// wraps bladerf_tuning_mode
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(i32)]
+pub enum TuningMode {
+    /// Tuning performed by libbladeRF on the host
+    Host = bladerf_tuning_mode_BLADERF_TUNING_MODE_HOST,
+    /// Tuning performed by the FPGA, required for quick / scheduled retunes
+    Fpga = bladerf_tuning_mode_BLADERF_TUNING_MODE_FPGA,
+}
+
+impl TryFrom<bladerf_tuning_mode> for TuningMode {
+    type Error = bladerf_tuning_mode;
+
+    fn try_from(value: bladerf_tuning_mode) -> Result<Self, bladerf_tuning_mode> {
+        let v = match value {
+            bladerf_tuning_mode_BLADERF_TUNING_MODE_HOST => Self::Host,
+            bladerf_tuning_mode_BLADERF_TUNING_MODE_FPGA => Self::Fpga,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
+/// Saved tuning state for quick retunes, see [`BladeRF::get_quick_tune`]
+///
+/// wraps bladerf_quick_tune, whose contents are board specific and opaque
+#[derive(Copy, Clone)]
+pub struct QuickTune(bladerf_quick_tune);
+
+impl From<bladerf_quick_tune> for QuickTune {
+    fn from(qt: bladerf_quick_tune) -> Self {
+        Self(qt)
+    }
+}
+
+impl From<QuickTune> for bladerf_quick_tune {
+    fn from(qt: QuickTune) -> Self {
+        qt.0
+    }
+}
+
+impl fmt::Debug for QuickTune {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuickTune").finish_non_exhaustive()
+    }
+}
+
+/// IQ and DC offset correction
+///
+/// wraps bladerf_correction
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(u32)]
+pub enum Correction {
+    /// DC offset correction on the I channel
+    DcOffI = bladerf_correction_BLADERF_CORR_DCOFF_I,
+    /// DC offset correction on the Q channel
+    DcOffQ = bladerf_correction_BLADERF_CORR_DCOFF_Q,
+    /// IQ phase balance correction
+    Phase = bladerf_correction_BLADERF_CORR_PHASE,
+    /// IQ gain balance correction
+    Gain = bladerf_correction_BLADERF_CORR_GAIN,
+}
+
+impl TryFrom<bladerf_correction> for Correction {
+    type Error = bladerf_correction;
+
+    fn try_from(value: bladerf_correction) -> Result<Self, bladerf_correction> {
+        let v = match value {
+            bladerf_correction_BLADERF_CORR_DCOFF_I => Self::DcOffI,
+            bladerf_correction_BLADERF_CORR_DCOFF_Q => Self::DcOffQ,
+            bladerf_correction_BLADERF_CORR_PHASE => Self::Phase,
+            bladerf_correction_BLADERF_CORR_GAIN => Self::Gain,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
+/// LMS6002D LNA gain (bladeRF 1 only)
+///
+/// wraps bladerf_lna_gain
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(u32)]
+pub enum LnaGain {
+    Unknown = bladerf_lna_gain_BLADERF_LNA_GAIN_UNKNOWN,
+    /// LNA bypassed, 0 dB
+    Bypass = bladerf_lna_gain_BLADERF_LNA_GAIN_BYPASS,
+    /// Mid gain, MAX - 6 dB
+    Mid = bladerf_lna_gain_BLADERF_LNA_GAIN_MID,
+    /// Maximum gain
+    Max = bladerf_lna_gain_BLADERF_LNA_GAIN_MAX,
+}
+
+impl TryFrom<bladerf_lna_gain> for LnaGain {
+    type Error = bladerf_lna_gain;
+
+    fn try_from(value: bladerf_lna_gain) -> Result<Self, bladerf_lna_gain> {
+        let v = match value {
+            bladerf_lna_gain_BLADERF_LNA_GAIN_UNKNOWN => Self::Unknown,
+            bladerf_lna_gain_BLADERF_LNA_GAIN_BYPASS => Self::Bypass,
+            bladerf_lna_gain_BLADERF_LNA_GAIN_MID => Self::Mid,
+            bladerf_lna_gain_BLADERF_LNA_GAIN_MAX => Self::Max,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
+/// Sample format
+///
+/// wraps bladerf_format
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(u32)]
+pub enum Format {
+    /// Signed, interleaved 16-bit I/Q with 11 fractional bits
+    Sc16Q11 = bladerf_format_BLADERF_FORMAT_SC16_Q11,
+    /// SC16 Q11 with metadata
+    Sc16Q11Meta = bladerf_format_BLADERF_FORMAT_SC16_Q11_META,
+    /// Raw packets with metadata
+    PacketMeta = bladerf_format_BLADERF_FORMAT_PACKET_META,
+    /// Signed, interleaved 8-bit I/Q with 7 fractional bits
+    Sc8Q7 = bladerf_format_BLADERF_FORMAT_SC8_Q7,
+    /// SC8 Q7 with metadata
+    Sc8Q7Meta = bladerf_format_BLADERF_FORMAT_SC8_Q7_META,
+}
+
+impl TryFrom<bladerf_format> for Format {
+    type Error = bladerf_format;
+
+    fn try_from(value: bladerf_format) -> Result<Self, bladerf_format> {
+        let v = match value {
+            bladerf_format_BLADERF_FORMAT_SC16_Q11 => Self::Sc16Q11,
+            bladerf_format_BLADERF_FORMAT_SC16_Q11_META => Self::Sc16Q11Meta,
+            bladerf_format_BLADERF_FORMAT_PACKET_META => Self::PacketMeta,
+            bladerf_format_BLADERF_FORMAT_SC8_Q7 => Self::Sc8Q7,
+            bladerf_format_BLADERF_FORMAT_SC8_Q7_META => Self::Sc8Q7Meta,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
+/// libbladeRF log verbosity
+///
+/// wraps bladerf_log_level
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(u32)]
+pub enum LogLevel {
+    Verbose = bladerf_log_level_BLADERF_LOG_LEVEL_VERBOSE,
+    Debug = bladerf_log_level_BLADERF_LOG_LEVEL_DEBUG,
+    Info = bladerf_log_level_BLADERF_LOG_LEVEL_INFO,
+    Warning = bladerf_log_level_BLADERF_LOG_LEVEL_WARNING,
+    Error = bladerf_log_level_BLADERF_LOG_LEVEL_ERROR,
+    Critical = bladerf_log_level_BLADERF_LOG_LEVEL_CRITICAL,
+    Silent = bladerf_log_level_BLADERF_LOG_LEVEL_SILENT,
+}
+
+impl TryFrom<bladerf_log_level> for LogLevel {
+    type Error = bladerf_log_level;
+
+    fn try_from(value: bladerf_log_level) -> Result<Self, bladerf_log_level> {
+        let v = match value {
+            bladerf_log_level_BLADERF_LOG_LEVEL_VERBOSE => Self::Verbose,
+            bladerf_log_level_BLADERF_LOG_LEVEL_DEBUG => Self::Debug,
+            bladerf_log_level_BLADERF_LOG_LEVEL_INFO => Self::Info,
+            bladerf_log_level_BLADERF_LOG_LEVEL_WARNING => Self::Warning,
+            bladerf_log_level_BLADERF_LOG_LEVEL_ERROR => Self::Error,
+            bladerf_log_level_BLADERF_LOG_LEVEL_CRITICAL => Self::Critical,
+            bladerf_log_level_BLADERF_LOG_LEVEL_SILENT => Self::Silent,
+            _ => return Err(value),
+        };
+
+        Ok(v)
+    }
+}
+
 impl Drop for BladeRF {
     fn drop(&mut self) {
+        // Streams may outlive their handles if leaked, so must be stopped first
+        let streams = self
+            .async_streams
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner());
+        for stream in mem::take(streams) {
+            let _ = stream.join(true);
+        }
+
         // Safety: the open functions will initialize self.device
         //    and make it null or a valid pointer.
         unsafe { bladerf_close(self.device) }
@@ -116,44 +605,68 @@ pub fn set_usb_reset_on_open(enabled: bool) {
     }
 }
 
+/// Set the libbladeRF log verbosity (applies to all devices)
+pub fn set_log_verbosity(level: LogLevel) {
+    unsafe {
+        bladerf_log_set_verbosity(level as bladerf_log_level);
+    }
+}
+
+/// Fetch the libbladeRF library version
+pub fn library_version() -> Version {
+    let mut version = bladerf_version {
+        major: 0,
+        minor: 0,
+        patch: 0,
+        describe: std::ptr::null::<i8>(),
+    };
+
+    // Safety: populates version with a static describe string
+    unsafe {
+        bladerf_version(&mut version);
+        Version::from_raw(&version)
+    }
+}
+
+/// Maximum number of gain reads used by [`BladeRF::set_gain`] to settle on the applied gain
+pub const GAIN_READBACK_ATTEMPTS: usize = 3;
+
+/// Convert a gain stage name for libbladeRF
+fn stage_name(
+    stage: &str,
+    channel: Channel,
+    op: &'static str,
+) -> Result<ffi::CString, BladeRfError> {
+    ffi::CString::new(stage).map_err(|_| {
+        BladeRfError::new(ErrorKind::Inval, op)
+            .with_channel(channel)
+            .with_detail(format!("invalid gain stage '{}'", stage))
+    })
+}
+
 impl BladeRF {
     /// List attached BladeRF devices
-    pub fn get_device_list() -> Result<Vec<bladerf_devinfo>, isize> {
-        let mut devices = MaybeUninit::<*mut bladerf_devinfo>::uninit();
-
-        // Safety: This function is responsible for initializing the devices pointer
-        // It will return n>0 if initialized.
-        // https://github.com/Nuand/bladeRF/blob/fe3304d75967c88ab4f17ff37cb5daf8ff53d3e1/host/libraries/libbladeRF/src/devinfo.c#L58
-        // Does this memory only need to be freed if it is sucessfully initialized?
-        let n = unsafe { bladerf_get_device_list(devices.as_mut_ptr()) as isize };
-
-        // Catch bladerf function errors
-        if n > 0 {
-            // Cast array to slice and create a safe array to return
-            // Safety: I think this is and will cause UB (out of bounds access) for anything beyond a single device.
-            //  the get_device_list seems to populate an "array" of pointers to bladerf_devinfo
-            let device_slice = unsafe { std::slice::from_raw_parts(*devices.as_ptr(), n as usize) };
-            let mut safe_device_list: Vec<bladerf_devinfo> = Vec::new();
-
-            for i in 0..n {
-                let local_device = device_slice[i as usize];
-                //Safe if this is a copy, unsafe if it is not?
-                safe_device_list.push(local_device);
-            }
-            unsafe { bladerf_free_device_list(*devices.as_ptr()) };
+    ///
+    /// Returns an empty list when no devices are attached
+    pub fn get_device_list() -> Result<Vec<DeviceInfo>, BladeRfError> {
+        device_info::device_list(&device_info::LibBladeRf, Backend::Any)
+    }
 
-            // Return rust save device info array
-            Ok(safe_device_list)
-        } else {
-            // Return error code
-            Err(n)
-        }
+    /// List attached BladeRF devices using the specified backend
+    ///
+    /// [`Backend::Any`] matches all devices
+    pub fn get_device_list_by_backend(backend: Backend) -> Result<Vec<DeviceInfo>, BladeRfError> {
+        device_info::device_list(&device_info::LibBladeRf, backend)
     }
 
     /// Open a BladeRF device by identifier
-    pub fn open(identifier: Option<String>) -> Result<Self, isize> {
+    pub fn open(identifier: Option<String>) -> Result<Self, BladeRfError> {
         let mut bladerf_device = Self {
             device: ptr::null_mut(),
+            validate: Default::default(),
+            streams: Default::default(),
+            sync_formats: Default::default(),
+            async_streams: Default::default(),
         };
 
         // Safety: This function is responsible for initializing the device pointer.
@@ -161,21 +674,52 @@ impl BladeRF {
         // It will either assign it null or a valid pointer
         let res = match identifier {
             Some(id) => {
-                let c_string = ffi::CString::new(id).unwrap();
+                let c_string = ffi::CString::new(id)
+                    .map_err(|_| BladeRfError::new(ErrorKind::Inval, "bladerf_open"))?;
                 unsafe { bladerf_open(&mut bladerf_device.device, c_string.as_ptr()) }
             }
             None => unsafe { bladerf_open(&mut bladerf_device.device, ptr::null()) },
         };
 
-        handle_res!(res, bladerf_device);
+        handle_res!(res, "bladerf_open", bladerf_device);
+    }
+
+    /// Open a BladeRF device by identifier, failing if it does not meet minimum versions
+    pub fn open_with_min_versions(
+        identifier: Option<String>,
+        min: &MinVersions,
+    ) -> Result<Self, BladeRfError> {
+        // Check the library before touching any devices
+        min.check(None, None, Some(&library_version()))?;
+
+        let device = Self::open(identifier)?;
+        device.check_min_versions(min)?;
+
+        Ok(device)
+    }
+
+    /// Open the first attached BladeRF device matching a selector
+    pub fn open_with(selector: &DeviceSelector) -> Result<Self, BladeRfError> {
+        let devices = Self::get_device_list()?;
+
+        match devices.iter().find(|d| selector.matches(d)) {
+            Some(d) => Self::open_with_devinfo(d),
+            None => Err(BladeRfError::new(ErrorKind::Nodev, "BladeRF::open_with")
+                .with_detail(format!("no device matching '{}'", selector))),
+        }
     }
 
-    /// Open a BladeRF device by devinfo object
-    pub fn open_with_devinfo(mut devinfo: bladerf_devinfo) -> Result<Self, isize> {
+    /// Open a BladeRF device by device info object
+    pub fn open_with_devinfo(devinfo: &DeviceInfo) -> Result<Self, BladeRfError> {
+        let mut devinfo = devinfo.to_devinfo();
         let devinfo_ptr: *mut bladerf_devinfo = &mut devinfo as *mut bladerf_devinfo;
 
         let mut bladerf_device = Self {
             device: ptr::null_mut(),
+            validate: Default::default(),
+            streams: Default::default(),
+            sync_formats: Default::default(),
+            async_streams: Default::default(),
         };
 
         // Safety: This function is responsible for initializing the device pointer.
@@ -183,14 +727,38 @@ impl BladeRF {
         // It will either assign it null or a valid pointer
         let res = unsafe { bladerf_open_with_devinfo(&mut bladerf_device.device, devinfo_ptr) };
 
-        handle_res!(res, bladerf_device);
+        handle_res!(res, "bladerf_open_with_devinfo", bladerf_device);
     }
 
     // Device Properties and Information
     // http://www.nuand.com/libbladeRF-doc/v1.7.2/group___f_n___i_n_f_o.html
 
+    /// Fetch the board model
+    pub fn board(&self) -> Result<Board, BladeRfError> {
+        // Safety: returns a pointer to a static string, or null on failure
+        let name = unsafe { bladerf_get_board_name(self.device) };
+        if name.is_null() {
+            return Err(BladeRfError::new(
+                ErrorKind::Unexpected,
+                "bladerf_get_board_name",
+            ));
+        }
+
+        let name = unsafe { ffi::CStr::from_ptr(name) }.to_string_lossy();
+
+        Board::try_from(name.as_ref()).map_err(|_| {
+            BladeRfError::new(ErrorKind::Unsupported, "bladerf_get_board_name")
+                .with_detail(format!("unknown board '{}'", name))
+        })
+    }
+
+    /// Fetch the capabilities of the attached board
+    pub fn capabilities(&self) -> Result<Capabilities, BladeRfError> {
+        Ok(self.board()?.capabilities())
+    }
+
     /// Gets the serial number of the BladeRF
-    pub fn get_serial(&self) -> Result<String, isize> {
+    pub fn get_serial(&self) -> Result<String, BladeRfError> {
         // Create raw data array for serial return
         let mut serial_data: Vec<::libc::c_char> = vec![0; 33];
 
@@ -210,23 +778,29 @@ impl BladeRF {
             //   it is initialized it with zeros,
             //   so I presume that this function will only write up to 32 characters and not touch the last value.
             let serial_cstr = unsafe { ffi::CString::from_vec_unchecked(serial_u8) };
-            let serial_str = serial_cstr.into_string().unwrap();
+            let serial_str = serial_cstr
+                .into_string()
+                .map_err(|_| BladeRfError::new(ErrorKind::Unexpected, "bladerf_get_serial"))?;
 
             Ok(serial_str)
         } else {
-            Err(res as isize)
+            Err(BladeRfError::from_code(res, "bladerf_get_serial"))
         }
     }
 
-    pub fn get_fpga_size(&self) -> Result<bladerf_fpga_size, isize> {
+    pub fn get_fpga_size(&self) -> Result<FpgaSize, BladeRfError> {
         let mut fpga_size: bladerf_fpga_size = bladerf_fpga_size_BLADERF_FPGA_UNKNOWN;
 
         let res = unsafe { bladerf_get_fpga_size(self.device, &mut fpga_size) };
 
-        handle_res!(res, fpga_size);
+        handle_res!(
+            res,
+            "bladerf_get_fpga_size",
+            FpgaSize::try_from(fpga_size).unwrap_or(FpgaSize::Unknown)
+        );
     }
 
-    pub fn fw_version(&self) -> Result<bladerf_version, isize> {
+    pub fn fw_version(&self) -> Result<Version, BladeRfError> {
         let mut version = bladerf_version {
             major: 0,
             minor: 0,
@@ -236,20 +810,23 @@ impl BladeRF {
 
         let res = unsafe { bladerf_fw_version(self.device, &mut version) };
 
-        handle_res!(res, version);
+        // Safety: describe is owned by the device and copied before this returns
+        handle_res!(res, "bladerf_fw_version", unsafe {
+            Version::from_raw(&version)
+        });
     }
 
-    pub fn is_fpga_configured(&self) -> Result<bool, isize> {
+    pub fn is_fpga_configured(&self) -> Result<bool, BladeRfError> {
         let res = unsafe { bladerf_is_fpga_configured(self.device) };
 
         match res.cmp(&0) {
             Ordering::Greater => Ok(true),
             Ordering::Equal => Ok(false),
-            Ordering::Less => Err(res as isize),
+            Ordering::Less => Err(BladeRfError::from_code(res, "bladerf_is_fpga_configured")),
         }
     }
 
-    pub fn fpga_version(&self) -> Result<bladerf_version, isize> {
+    pub fn fpga_version(&self) -> Result<Version, BladeRfError> {
         let mut version = bladerf_version {
             major: 0,
             minor: 0,
@@ -259,113 +836,487 @@ impl BladeRF {
 
         let res = unsafe { bladerf_fpga_version(self.device, &mut version) };
 
-        handle_res!(res, version);
+        // Safety: describe is owned by the device and copied before this returns
+        handle_res!(res, "bladerf_fpga_version", unsafe {
+            Version::from_raw(&version)
+        });
+    }
+
+    /// Fetch the source the FPGA was configured from
+    pub fn get_fpga_source(&self) -> Result<FpgaSource, BladeRfError> {
+        let mut source = bladerf_fpga_source_BLADERF_FPGA_SOURCE_UNKNOWN;
+
+        let res = unsafe { bladerf_get_fpga_source(self.device, &mut source) };
+
+        handle_res!(
+            res,
+            "bladerf_get_fpga_source",
+            FpgaSource::try_from(source).unwrap_or(FpgaSource::Unknown)
+        );
+    }
+
+    /// Fetch the flash size in bytes, and whether this was guessed from the FPGA size
+    pub fn get_flash_size(&self) -> Result<(u32, bool), BladeRfError> {
+        let mut size = 0u32;
+        let mut is_guess = false;
+
+        let res = unsafe { bladerf_get_flash_size(self.device, &mut size, &mut is_guess) };
+
+        handle_res!(res, "bladerf_get_flash_size", (size, is_guess));
+    }
+
+    /// Fetch the USB connection speed
+    pub fn usb_speed(&self) -> UsbSpeed {
+        let speed = unsafe { bladerf_device_speed(self.device) };
+
+        UsbSpeed::try_from(speed).unwrap_or(UsbSpeed::Unknown)
+    }
+
+    /// Gather identity and version information for the device
+    pub fn report(&self) -> Result<DeviceReport, BladeRfError> {
+        let firmware = self.fw_version()?;
+
+        let fpga_configured = self.is_fpga_configured()?;
+        let fpga = match fpga_configured {
+            true => Some(self.fpga_version()?),
+            false => None,
+        };
+
+        let fpga_size = self.get_fpga_size()?;
+        let (flash_size, flash_size_guessed) = self.get_flash_size()?;
+
+        // Firmware without FPGA source support reports Unsupported on bladeRF1
+        let fpga_source = match self.get_fpga_source() {
+            Err(e) if e.kind() == ErrorKind::Unsupported => FpgaSource::Unknown,
+            res => res?,
+        };
+
+        Ok(DeviceReport {
+            serial: self.get_serial()?,
+            board: self.board()?,
+            usb_speed: self.usb_speed(),
+            firmware,
+            fpga,
+            fpga_configured,
+            fpga_size,
+            fpga_source,
+            flash_size,
+            flash_size_guessed,
+            library: library_version(),
+        })
+    }
+
+    /// Check device and library versions against minimum requirements
+    ///
+    /// A required FPGA version fails with [`ErrorKind::UpdateFpga`] when the FPGA
+    /// is not configured
+    pub fn check_min_versions(&self, min: &MinVersions) -> Result<(), BladeRfError> {
+        let firmware = self.fw_version()?;
+        let fpga = match self.is_fpga_configured()? {
+            true => Some(self.fpga_version()?),
+            false => None,
+        };
+
+        if let (Some(required), None) = (&min.fpga, &fpga) {
+            return Err(
+                BladeRfError::new(ErrorKind::UpdateFpga, "BladeRF::check_min_versions")
+                    .with_detail(format!("FPGA is not loaded, version {} required", required)),
+            );
+        }
+
+        min.check(Some(&firmware), fpga.as_ref(), Some(&library_version()))
+    }
+
+    // Parameter ranges
+
+    /// Enable or disable range validation in `set_frequency`, `set_sample_rate`,
+    /// `set_bandwidth` and `set_gain` (disabled by default)
+    ///
+    /// When enabled, out of range values are rejected with [`ErrorKind::Range`]
+    /// before the hardware is touched.
+    pub fn set_range_validation(&self, enabled: bool) {
+        self.validate
+            .store(enabled, sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Fetch whether range validation is enabled
+    pub fn range_validation(&self) -> bool {
+        self.validate.load(sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Fetch the tuning range of a channel, in Hz
+    pub fn get_frequency_range(&self, channel: Channel) -> Result<Range, BladeRfError> {
+        self.get_range(channel, "bladerf_get_frequency_range", |r| unsafe {
+            bladerf_get_frequency_range(self.device, channel.into(), r)
+        })
+    }
+
+    /// Fetch the sample rate range of a channel, in samples per second
+    pub fn get_sample_rate_range(&self, channel: Channel) -> Result<Range, BladeRfError> {
+        self.get_range(channel, "bladerf_get_sample_rate_range", |r| unsafe {
+            bladerf_get_sample_rate_range(self.device, channel.into(), r)
+        })
+    }
+
+    /// Fetch the bandwidth range of a channel, in Hz
+    pub fn get_bandwidth_range(&self, channel: Channel) -> Result<Range, BladeRfError> {
+        self.get_range(channel, "bladerf_get_bandwidth_range", |r| unsafe {
+            bladerf_get_bandwidth_range(self.device, channel.into(), r)
+        })
+    }
+
+    /// Fetch the overall gain range of a channel, in dB
+    pub fn get_gain_range(&self, channel: Channel) -> Result<Range, BladeRfError> {
+        self.get_range(channel, "bladerf_get_gain_range", |r| unsafe {
+            bladerf_get_gain_range(self.device, channel.into(), r)
+        })
+    }
+
+    fn get_range(
+        &self,
+        channel: Channel,
+        op: &'static str,
+        f: impl FnOnce(*mut *const bladerf_range) -> i32,
+    ) -> Result<Range, BladeRfError> {
+        let mut range: *const bladerf_range = ptr::null();
+
+        let res = f(&mut range);
+        if res < 0 {
+            return Err(BladeRfError::from_code(res, op).with_channel(channel));
+        }
+
+        // Safety: on success libbladeRF points range at a static structure
+        match unsafe { range.as_ref() } {
+            Some(r) => Ok(Range::from(r)),
+            None => Err(BladeRfError::new(ErrorKind::Unexpected, op).with_channel(channel)),
+        }
+    }
+
+    /// Check a value against a range when validation is enabled
+    fn validate(
+        &self,
+        channel: Channel,
+        value: f64,
+        op: &'static str,
+        name: &str,
+        unit: &str,
+        range: impl FnOnce() -> Result<Range, BladeRfError>,
+    ) -> Result<(), BladeRfError> {
+        if !self.range_validation() {
+            return Ok(());
+        }
+
+        range::check(&range()?, value, op, channel, name, unit)
     }
 
     // RX & TX Module Control
     // http://www.nuand.com/libbladeRF-doc/v1.7.2/group___f_n___m_o_d_u_l_e.html
 
-    pub fn enable_module(&self, module: bladerf_module, enable: bool) -> Result<isize, isize> {
-        let res = unsafe { bladerf_enable_module(self.device, module, enable) };
+    pub fn enable_module(&self, channel: Channel, enable: bool) -> Result<(), BladeRfError> {
+        let res = unsafe { bladerf_enable_module(self.device, channel.into(), enable) };
 
-        handle_res!(res);
+        handle_res!(res, "bladerf_enable_module", channel = channel);
     }
 
     // Gain Control
     // http://www.nuand.com/libbladeRF-doc/v1.7.2/group___f_n___g_a_i_n.html
 
-    pub fn set_lna_gain(&self, gain: bladerf_lna_gain) -> Result<isize, isize> {
-        let res = unsafe { bladerf_set_lna_gain(self.device, gain) };
+    pub fn set_lna_gain(&self, gain: LnaGain) -> Result<(), BladeRfError> {
+        let res = unsafe { bladerf_set_lna_gain(self.device, gain as bladerf_lna_gain) };
 
-        handle_res!(res);
+        handle_res!(res, "bladerf_set_lna_gain");
     }
 
-    pub fn get_lna_gain(&self) -> Result<bladerf_lna_gain, isize> {
+    pub fn get_lna_gain(&self) -> Result<LnaGain, BladeRfError> {
         let mut gain: bladerf_lna_gain = bladerf_lna_gain_BLADERF_LNA_GAIN_UNKNOWN;
 
         let res = unsafe { bladerf_get_lna_gain(self.device, &mut gain) };
 
-        handle_res!(res, gain);
+        handle_res!(
+            res,
+            "bladerf_get_lna_gain",
+            LnaGain::try_from(gain).unwrap_or(LnaGain::Unknown)
+        );
     }
 
-    pub fn set_rxvga1(&self, gain: i32) -> Result<isize, isize> {
-        let res = unsafe { bladerf_set_rxvga1(self.device, gain) };
+    #[deprecated(note = "use set_gain_stage with the \"rxvga1\" stage")]
+    pub fn set_rxvga1(&self, gain: Decibels) -> Result<(), BladeRfError> {
+        let res = unsafe { bladerf_set_rxvga1(self.device, gain.0) };
 
-        handle_res!(res);
+        handle_res!(res, "bladerf_set_rxvga1");
     }
 
-    pub fn get_rxvga1(&self) -> Result<i32, isize> {
+    #[deprecated(note = "use get_gain_stage with the \"rxvga1\" stage")]
+    pub fn get_rxvga1(&self) -> Result<Decibels, BladeRfError> {
         let mut gain: i32 = 0;
 
         let res = unsafe { bladerf_get_rxvga1(self.device, &mut gain) };
 
-        handle_res!(res, gain);
+        handle_res!(res, "bladerf_get_rxvga1", Decibels(gain));
     }
 
-    pub fn set_rxvga2(&self, gain: i32) -> Result<isize, isize> {
-        let res = unsafe { bladerf_set_rxvga2(self.device, gain) };
+    #[deprecated(note = "use set_gain_stage with the \"rxvga2\" stage")]
+    pub fn set_rxvga2(&self, gain: Decibels) -> Result<(), BladeRfError> {
+        let res = unsafe { bladerf_set_rxvga2(self.device, gain.0) };
 
-        handle_res!(res);
+        handle_res!(res, "bladerf_set_rxvga2");
     }
 
-    pub fn get_rxvga2(&self) -> Result<i32, isize> {
+    #[deprecated(note = "use get_gain_stage with the \"rxvga2\" stage")]
+    pub fn get_rxvga2(&self) -> Result<Decibels, BladeRfError> {
         let mut gain: i32 = 0;
 
         let res = unsafe { bladerf_get_rxvga2(self.device, &mut gain) };
 
-        handle_res!(res, gain);
+        handle_res!(res, "bladerf_get_rxvga2", Decibels(gain));
     }
 
-    pub fn set_txvga1(&self, gain: i32) -> Result<isize, isize> {
-        let res = unsafe { bladerf_set_txvga1(self.device, gain) };
+    #[deprecated(note = "use set_gain_stage with the \"txvga1\" stage")]
+    pub fn set_txvga1(&self, gain: Decibels) -> Result<(), BladeRfError> {
+        let res = unsafe { bladerf_set_txvga1(self.device, gain.0) };
 
-        handle_res!(res);
+        handle_res!(res, "bladerf_set_txvga1");
     }
 
-    pub fn get_txvga1(&self) -> Result<i32, isize> {
+    #[deprecated(note = "use get_gain_stage with the \"txvga1\" stage")]
+    pub fn get_txvga1(&self) -> Result<Decibels, BladeRfError> {
         let mut gain: i32 = 0;
 
         let res = unsafe { bladerf_get_txvga1(self.device, &mut gain) };
 
-        handle_res!(res, gain);
+        handle_res!(res, "bladerf_get_txvga1", Decibels(gain));
     }
 
-    pub fn set_txvga2(&self, gain: i32) -> Result<isize, isize> {
-        let res = unsafe { bladerf_set_txvga2(self.device, gain) };
+    #[deprecated(note = "use set_gain_stage with the \"txvga2\" stage")]
+    pub fn set_txvga2(&self, gain: Decibels) -> Result<(), BladeRfError> {
+        let res = unsafe { bladerf_set_txvga2(self.device, gain.0) };
 
-        handle_res!(res);
+        handle_res!(res, "bladerf_set_txvga2");
     }
 
-    pub fn get_txvga2(&self) -> Result<i32, isize> {
+    #[deprecated(note = "use get_gain_stage with the \"txvga2\" stage")]
+    pub fn get_txvga2(&self) -> Result<Decibels, BladeRfError> {
         let mut gain: i32 = 0;
 
         let res = unsafe { bladerf_get_txvga2(self.device, &mut gain) };
 
-        handle_res!(res, gain);
+        handle_res!(res, "bladerf_get_txvga2", Decibels(gain));
     }
 
-    pub fn set_gain(&self, module: bladerf_module, gain: i32) -> Result<isize, isize> {
-        let res = unsafe { bladerf_set_gain(self.device, module, gain) };
+    /// Set the overall gain of a channel, returning the gain applied
+    ///
+    /// The requested gain is quantized by the hardware, the value is read back
+    /// until consecutive reads agree (up to [`GAIN_READBACK_ATTEMPTS`] reads).
+    pub fn set_gain(&self, channel: Channel, gain: Decibels) -> Result<Decibels, BladeRfError> {
+        self.validate(
+            channel,
+            gain.0 as f64,
+            "bladerf_set_gain",
+            "gain",
+            "dB",
+            || self.get_gain_range(channel),
+        )?;
+
+        let res = unsafe { bladerf_set_gain(self.device, channel.into(), gain.0) };
+        if res < 0 {
+            return Err(BladeRfError::from_code(res, "bladerf_set_gain").with_channel(channel));
+        }
+
+        let mut actual = self.get_gain(channel)?;
+        for _ in 1..GAIN_READBACK_ATTEMPTS {
+            if actual == gain {
+                break;
+            }
+
+            let next = self.get_gain(channel)?;
+            if next == actual {
+                break;
+            }
+            actual = next;
+        }
+
+        Ok(actual)
+    }
+
+    /// Fetch the overall gain of a channel
+    pub fn get_gain(&self, channel: Channel) -> Result<Decibels, BladeRfError> {
+        let mut gain = 0;
+
+        let res = unsafe { bladerf_get_gain(self.device, channel.into(), &mut gain) };
+
+        handle_res!(res, "bladerf_get_gain", channel = channel, Decibels(gain));
+    }
+
+    /// Set the gain control mode of a channel
+    ///
+    /// Only RX channels support AGC, see [`BladeRF::get_gain_modes`]
+    pub fn set_gain_mode(&self, channel: Channel, mode: GainMode) -> Result<(), BladeRfError> {
+        let res = unsafe {
+            bladerf_set_gain_mode(self.device, channel.into(), mode as bladerf_gain_mode)
+        };
 
-        handle_res!(res);
+        handle_res!(res, "bladerf_set_gain_mode", channel = channel);
+    }
+
+    /// Fetch the gain control mode of a channel
+    pub fn get_gain_mode(&self, channel: Channel) -> Result<GainMode, BladeRfError> {
+        let mut mode = bladerf_gain_mode_BLADERF_GAIN_DEFAULT;
+
+        let res = unsafe { bladerf_get_gain_mode(self.device, channel.into(), &mut mode) };
+        if res < 0 {
+            return Err(BladeRfError::from_code(res, "bladerf_get_gain_mode").with_channel(channel));
+        }
+
+        GainMode::try_from(mode).map_err(|v| {
+            BladeRfError::new(ErrorKind::Unexpected, "bladerf_get_gain_mode")
+                .with_channel(channel)
+                .with_detail(format!("unknown gain mode {}", v))
+        })
+    }
+
+    /// List the gain control modes supported by a channel
+    pub fn get_gain_modes(&self, channel: Channel) -> Result<Vec<GainMode>, BladeRfError> {
+        let mut modes: *const bladerf_gain_modes = ptr::null();
+
+        let res = unsafe { bladerf_get_gain_modes(self.device, channel.into(), &mut modes) };
+        if res < 0 {
+            return Err(
+                BladeRfError::from_code(res, "bladerf_get_gain_modes").with_channel(channel)
+            );
+        }
+        if modes.is_null() {
+            return Ok(vec![]);
+        }
+
+        // Safety: on success modes points to a static array of res entries
+        let modes = unsafe { slice::from_raw_parts(modes, res as usize) };
+
+        Ok(modes
+            .iter()
+            .filter_map(|m| GainMode::try_from(m.mode).ok())
+            .collect())
+    }
+
+    /// List the names of the gain stages of a channel
+    pub fn get_gain_stages(&self, channel: Channel) -> Result<Vec<String>, BladeRfError> {
+        // Fetch the number of stages, then the stage names
+        let res =
+            unsafe { bladerf_get_gain_stages(self.device, channel.into(), ptr::null_mut(), 0) };
+        if res < 0 {
+            return Err(
+                BladeRfError::from_code(res, "bladerf_get_gain_stages").with_channel(channel)
+            );
+        }
+
+        let mut stages: Vec<*const libc::c_char> = vec![ptr::null(); res as usize];
+
+        let res = unsafe {
+            bladerf_get_gain_stages(
+                self.device,
+                channel.into(),
+                stages.as_mut_ptr(),
+                stages.len(),
+            )
+        };
+        if res < 0 {
+            return Err(
+                BladeRfError::from_code(res, "bladerf_get_gain_stages").with_channel(channel)
+            );
+        }
+
+        // Safety: populated entries point to static null terminated strings
+        Ok(stages
+            .iter()
+            .take(res as usize)
+            .filter(|s| !s.is_null())
+            .map(|s| {
+                unsafe { ffi::CStr::from_ptr(*s) }
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect())
+    }
+
+    /// Set the gain of a named stage
+    pub fn set_gain_stage(
+        &self,
+        channel: Channel,
+        stage: &str,
+        gain: Decibels,
+    ) -> Result<(), BladeRfError> {
+        let stage = stage_name(stage, channel, "bladerf_set_gain_stage")?;
+
+        let res =
+            unsafe { bladerf_set_gain_stage(self.device, channel.into(), stage.as_ptr(), gain.0) };
+
+        handle_res!(res, "bladerf_set_gain_stage", channel = channel);
+    }
+
+    /// Fetch the gain of a named stage
+    pub fn get_gain_stage(&self, channel: Channel, stage: &str) -> Result<Decibels, BladeRfError> {
+        let stage = stage_name(stage, channel, "bladerf_get_gain_stage")?;
+        let mut gain = 0;
+
+        let res = unsafe {
+            bladerf_get_gain_stage(self.device, channel.into(), stage.as_ptr(), &mut gain)
+        };
+
+        handle_res!(
+            res,
+            "bladerf_get_gain_stage",
+            channel = channel,
+            Decibels(gain)
+        );
+    }
+
+    /// Fetch the gain range of a named stage, in dB
+    pub fn get_gain_stage_range(
+        &self,
+        channel: Channel,
+        stage: &str,
+    ) -> Result<Range, BladeRfError> {
+        let stage = stage_name(stage, channel, "bladerf_get_gain_stage_range")?;
+
+        self.get_range(channel, "bladerf_get_gain_stage_range", |r| unsafe {
+            bladerf_get_gain_stage_range(self.device, channel.into(), stage.as_ptr(), r)
+        })
     }
 
     // Sampling Control
 
-    pub fn set_sample_rate(&self, module: bladerf_module, rate: u32) -> Result<u32, isize> {
+    pub fn set_sample_rate(
+        &self,
+        channel: Channel,
+        rate: SampleRate,
+    ) -> Result<SampleRate, BladeRfError> {
+        self.validate(
+            channel,
+            rate.0 as f64,
+            "bladerf_set_sample_rate",
+            "sample rate",
+            "sps",
+            || self.get_sample_rate_range(channel),
+        )?;
+
         let mut actual: u32 = 0;
 
-        let res = unsafe { bladerf_set_sample_rate(self.device, module, rate, &mut actual) };
+        let res =
+            unsafe { bladerf_set_sample_rate(self.device, channel.into(), rate.0, &mut actual) };
 
-        handle_res!(res, actual);
+        handle_res!(
+            res,
+            "bladerf_set_sample_rate",
+            channel = channel,
+            SampleRate(actual)
+        );
     }
 
     pub fn set_rational_sample_rate(
         &self,
-        module: bladerf_module,
-        rate: bladerf_rational_rate,
-    ) -> Result<bladerf_rational_rate, isize> {
-        let mut rate = rate;
+        channel: Channel,
+        rate: RationalRate,
+    ) -> Result<RationalRate, BladeRfError> {
+        let mut rate = rate.into();
 
         let mut actual = bladerf_rational_rate {
             integer: 0,
@@ -374,101 +1325,153 @@ impl BladeRF {
         };
 
         let res = unsafe {
-            bladerf_set_rational_sample_rate(self.device, module, &mut rate, &mut actual)
+            bladerf_set_rational_sample_rate(self.device, channel.into(), &mut rate, &mut actual)
         };
-        handle_res!(res, actual);
+        handle_res!(
+            res,
+            "bladerf_set_rational_sample_rate",
+            channel = channel,
+            actual.into()
+        );
     }
 
-    pub fn get_sample_rate(&self, module: bladerf_module) -> Result<u32, isize> {
+    pub fn get_sample_rate(&self, channel: Channel) -> Result<SampleRate, BladeRfError> {
         let mut rate: u32 = 0;
 
-        let res = unsafe { bladerf_get_sample_rate(self.device, module, &mut rate) };
+        let res = unsafe { bladerf_get_sample_rate(self.device, channel.into(), &mut rate) };
 
-        handle_res!(res, rate);
+        handle_res!(
+            res,
+            "bladerf_get_sample_rate",
+            channel = channel,
+            SampleRate(rate)
+        );
     }
 
-    pub fn get_rational_sample_rate(
-        &self,
-        module: bladerf_module,
-    ) -> Result<bladerf_rational_rate, isize> {
+    pub fn get_rational_sample_rate(&self, channel: Channel) -> Result<RationalRate, BladeRfError> {
         let mut rate = bladerf_rational_rate {
             integer: 0,
             num: 0,
             den: 0,
         };
 
-        let res = unsafe { bladerf_get_rational_sample_rate(self.device, module, &mut rate) };
+        let res =
+            unsafe { bladerf_get_rational_sample_rate(self.device, channel.into(), &mut rate) };
 
-        handle_res!(res, rate);
+        handle_res!(
+            res,
+            "bladerf_get_rational_sample_rate",
+            channel = channel,
+            rate.into()
+        );
     }
 
-    pub fn set_sampling(&self, sampling: bladerf_sampling) -> Result<isize, isize> {
-        let res = unsafe { bladerf_set_sampling(self.device, sampling) };
+    pub fn set_sampling(&self, sampling: Sampling) -> Result<(), BladeRfError> {
+        let res = unsafe { bladerf_set_sampling(self.device, sampling as bladerf_sampling) };
 
-        handle_res!(res);
+        handle_res!(res, "bladerf_set_sampling");
     }
 
     /// Configure RX mux
-    pub fn set_rx_mux(&self, mux: bladerf_rx_mux) -> Result<isize, isize> {
-        let res = unsafe { bladerf_set_rx_mux(self.device, mux) };
+    pub fn set_rx_mux(&self, mux: RxMux) -> Result<(), BladeRfError> {
+        let res = unsafe { bladerf_set_rx_mux(self.device, mux as bladerf_rx_mux) };
 
-        handle_res!(res);
+        handle_res!(res, "bladerf_set_rx_mux");
     }
 
     /// Fetch RX mux information
-    pub fn get_rx_mux(&self) -> Result<bladerf_rx_mux, isize> {
-        let mut mux: bladerf_rx_mux = 0;
+    pub fn get_rx_mux(&self) -> Result<RxMux, BladeRfError> {
+        let mut mux: bladerf_rx_mux = bladerf_rx_mux_BLADERF_RX_MUX_INVALID;
 
         let res = unsafe { bladerf_get_rx_mux(self.device, &mut mux as *mut bladerf_rx_mux) };
+        if res < 0 {
+            return Err(BladeRfError::from_code(res, "bladerf_get_rx_mux"));
+        }
 
-        handle_res!(res, mux);
+        RxMux::try_from(mux).map_err(|v| {
+            BladeRfError::new(ErrorKind::Unexpected, "bladerf_get_rx_mux")
+                .with_detail(format!("unknown RX mux {}", v))
+        })
     }
 
     /// Fetch sampling rate
-    pub fn get_sampling(&self) -> Result<bladerf_sampling, isize> {
+    pub fn get_sampling(&self) -> Result<Sampling, BladeRfError> {
         let mut sampling = bladerf_sampling_BLADERF_SAMPLING_UNKNOWN;
 
         let res = unsafe { bladerf_get_sampling(self.device, &mut sampling) };
 
-        handle_res!(res, sampling);
+        handle_res!(
+            res,
+            "bladerf_get_sampling",
+            Sampling::try_from(sampling).unwrap_or(Sampling::Unknown)
+        );
     }
 
     /// Configure bandwidth
     ///
     /// See: http://www.nuand.com/libbladeRF-doc/v1.7.2/group___f_n___b_a_n_d_w_i_d_t_h.html
-    pub fn set_bandwidth(&self, module: bladerf_module, bandwidth: u32) -> Result<u32, isize> {
+    pub fn set_bandwidth(
+        &self,
+        channel: Channel,
+        bandwidth: Bandwidth,
+    ) -> Result<Bandwidth, BladeRfError> {
+        self.validate(
+            channel,
+            bandwidth.0 as f64,
+            "bladerf_set_bandwidth",
+            "bandwidth",
+            "Hz",
+            || self.get_bandwidth_range(channel),
+        )?;
+
         let mut actual: u32 = 0;
 
-        let res = unsafe { bladerf_set_bandwidth(self.device, module, bandwidth, &mut actual) };
+        let res =
+            unsafe { bladerf_set_bandwidth(self.device, channel.into(), bandwidth.0, &mut actual) };
 
-        handle_res!(res, actual);
+        handle_res!(
+            res,
+            "bladerf_set_bandwidth",
+            channel = channel,
+            Bandwidth(actual)
+        );
     }
 
     /// Fetch bandwidth information
-    pub fn get_bandwidth(&self, module: bladerf_module) -> Result<u32, isize> {
+    pub fn get_bandwidth(&self, channel: Channel) -> Result<Bandwidth, BladeRfError> {
         let mut bandwidth: u32 = 0;
 
-        let res = unsafe { bladerf_get_bandwidth(self.device, module, &mut bandwidth) };
+        let res = unsafe { bladerf_get_bandwidth(self.device, channel.into(), &mut bandwidth) };
 
-        handle_res!(res, bandwidth);
+        handle_res!(
+            res,
+            "bladerf_get_bandwidth",
+            channel = channel,
+            Bandwidth(bandwidth)
+        );
     }
 
-    pub fn set_lpf_mode(
-        &self,
-        module: bladerf_module,
-        lpf_mode: bladerf_lpf_mode,
-    ) -> Result<isize, isize> {
-        let res = unsafe { bladerf_set_lpf_mode(self.device, module, lpf_mode) };
+    pub fn set_lpf_mode(&self, channel: Channel, lpf_mode: LpfMode) -> Result<(), BladeRfError> {
+        let res = unsafe {
+            bladerf_set_lpf_mode(self.device, channel.into(), lpf_mode as bladerf_lpf_mode)
+        };
 
-        handle_res!(res);
+        handle_res!(res, "bladerf_set_lpf_mode", channel = channel);
     }
 
-    pub fn get_lpf_mode(&self, module: bladerf_module) -> Result<bladerf_lpf_mode, isize> {
+    pub fn get_lpf_mode(&self, channel: Channel) -> Result<LpfMode, BladeRfError> {
         let mut lpf_mode = bladerf_lpf_mode_BLADERF_LPF_NORMAL;
 
-        let res = unsafe { bladerf_get_lpf_mode(self.device, module, &mut lpf_mode) };
+        let res = unsafe { bladerf_get_lpf_mode(self.device, channel.into(), &mut lpf_mode) };
+        if res < 0 {
+            return Err(BladeRfError::from_code(res, "bladerf_get_lpf_mode").with_channel(channel));
+        }
 
-        handle_res!(res, lpf_mode);
+        LpfMode::try_from(lpf_mode).map_err(|v| {
+            BladeRfError::new(ErrorKind::Unexpected, "bladerf_get_lpf_mode")
+                .with_channel(channel)
+                .with_detail(format!("unknown LPF mode {}", v))
+        })
     }
 
     //bladerf_set_bandwidth (struct bladerf *dev, bladerf_module module, unsigned int bandwidth, unsigned int *actual)
@@ -479,47 +1482,54 @@ impl BladeRF {
     /// Set frequency band
     ///
     /// See: http://www.nuand.com/libbladeRF-doc/v1.7.2/group___f_n___t_u_n_i_n_g.html
-    pub fn select_band(&self, module: bladerf_module, frequency: u64) -> Result<isize, isize> {
-        let res = unsafe { bladerf_select_band(self.device, module, frequency) };
+    pub fn select_band(&self, channel: Channel, frequency: Hertz) -> Result<(), BladeRfError> {
+        let res = unsafe { bladerf_select_band(self.device, channel.into(), frequency.0) };
 
-        handle_res!(res);
+        handle_res!(res, "bladerf_select_band", channel = channel);
     }
 
     /// Set frequency
     ///
     /// See: http://www.nuand.com/libbladeRF-doc/v1.7.2/group___f_n___t_u_n_i_n_g.html
-    pub fn set_frequency(&self, channel: BladeRFChannel, frequency: u64) -> Result<isize, isize> {
-        let res =
-            unsafe { bladerf_set_frequency(self.device, channel as bladerf_channel, frequency) };
+    pub fn set_frequency(&self, channel: Channel, frequency: Hertz) -> Result<(), BladeRfError> {
+        self.validate(
+            channel,
+            frequency.0 as f64,
+            "bladerf_set_frequency",
+            "frequency",
+            "Hz",
+            || self.get_frequency_range(channel),
+        )?;
 
-        handle_res!(res);
+        let res = unsafe { bladerf_set_frequency(self.device, channel.into(), frequency.0) };
+
+        handle_res!(res, "bladerf_set_frequency", channel = channel);
     }
 
     /// Fetch frequyency
-    pub fn get_frequency(&self, channel: BladeRFChannel) -> Result<u64, isize> {
+    pub fn get_frequency(&self, channel: Channel) -> Result<Hertz, BladeRfError> {
         let mut freq: u64 = 0;
 
-        let res =
-            unsafe { bladerf_get_frequency(self.device, channel as bladerf_channel, &mut freq) };
+        let res = unsafe { bladerf_get_frequency(self.device, channel.into(), &mut freq) };
 
-        handle_res!(res, freq);
+        handle_res!(res, "bladerf_get_frequency", channel = channel, Hertz(freq));
     }
 
     /// Schedule retuning
     pub fn schedule_retune(
         &self,
-        module: bladerf_module,
+        channel: Channel,
         time: u64,
-        frequency: u64,
-        quick_tune: Option<bladerf_quick_tune>,
-    ) -> Result<isize, isize> {
+        frequency: Hertz,
+        quick_tune: Option<QuickTune>,
+    ) -> Result<(), BladeRfError> {
         let mut quick_tune_int: bladerf_quick_tune;
         let p: *mut bladerf_quick_tune;
 
         // Check whether quick tune exists and map pointer as appropriate
         match quick_tune {
             Some(qt) => {
-                quick_tune_int = qt;
+                quick_tune_int = qt.into();
                 p = &mut quick_tune_int;
             }
             None => {
@@ -528,60 +1538,78 @@ impl BladeRF {
         }
 
         // Call underlying function
-        let res = unsafe { bladerf_schedule_retune(self.device, module, time, frequency, p) };
+        let res =
+            unsafe { bladerf_schedule_retune(self.device, channel.into(), time, frequency.0, p) };
 
         // Process response
-        handle_res!(res)
+        handle_res!(res, "bladerf_schedule_retune", channel = channel)
     }
 
-    pub fn cancel_scheduled_retune(&self, module: bladerf_module) -> Result<isize, isize> {
-        let res = unsafe { bladerf_cancel_scheduled_retunes(self.device, module) } as isize;
+    pub fn cancel_scheduled_retune(&self, channel: Channel) -> Result<(), BladeRfError> {
+        let res = unsafe { bladerf_cancel_scheduled_retunes(self.device, channel.into()) };
 
-        handle_res!(res);
+        handle_res!(res, "bladerf_cancel_scheduled_retunes", channel = channel);
     }
 
-    #[cfg(feature = "unimplemented")]
-    pub fn get_quick_tune(&self, module: BladeRFChannel) -> Result<bladerf_quick_tune, isize> {
-        let mut quick_tune = bladerf_quick_tune {
-            freqsel: 0,
-            vcocap: 0,
-            nint: 0,
-            nfrac: 0,
-            flags: 0,
-        };
+    /// Save the current tuning state for use with [`BladeRF::schedule_retune`]
+    pub fn get_quick_tune(&self, channel: Channel) -> Result<QuickTune, BladeRfError> {
+        // Safety: bladerf_quick_tune is plain data, filled in by libbladeRF
+        let mut quick_tune: bladerf_quick_tune = unsafe { mem::zeroed() };
 
-        let res = unsafe { bladerf_get_quick_tune(self.device, module, &mut quick_tune) };
+        let res = unsafe { bladerf_get_quick_tune(self.device, channel.into(), &mut quick_tune) };
 
-        handle_res!(res, quick_tune);
+        handle_res!(
+            res,
+            "bladerf_get_quick_tune",
+            channel = channel,
+            quick_tune.into()
+        );
     }
 
-    pub fn set_tuning_mode(&self, mode: bladerf_tuning_mode) -> Result<isize, isize> {
-        let res = unsafe { bladerf_set_tuning_mode(self.device, mode) } as isize;
+    pub fn set_tuning_mode(&self, mode: TuningMode) -> Result<(), BladeRfError> {
+        let res = unsafe { bladerf_set_tuning_mode(self.device, mode as bladerf_tuning_mode) };
+
+        handle_res!(res, "bladerf_set_tuning_mode");
+    }
+
+    pub fn get_tuning_mode(&self) -> Result<TuningMode, BladeRfError> {
+        let mut mode = bladerf_tuning_mode_BLADERF_TUNING_MODE_INVALID;
+
+        let res = unsafe { bladerf_get_tuning_mode(self.device, &mut mode) };
+        if res < 0 {
+            return Err(BladeRfError::from_code(res, "bladerf_get_tuning_mode"));
+        }
 
-        handle_res!(res);
+        TuningMode::try_from(mode).map_err(|v| {
+            BladeRfError::new(ErrorKind::Unexpected, "bladerf_get_tuning_mode")
+                .with_detail(format!("unknown tuning mode {}", v))
+        })
     }
 
     /// Set internal loopback state
     ///
     /// See: http://www.nuand.com/libbladeRF-doc/v1.7.2/group___f_n___l_o_o_p_b_a_c_k.html
-    pub fn set_loopback(&self, loopback: BladeRFLoopback) -> Result<isize, isize> {
+    pub fn set_loopback(&self, loopback: BladeRFLoopback) -> Result<(), BladeRfError> {
         let res = unsafe { bladerf_set_loopback(self.device, loopback as bladerf_loopback) };
 
-        handle_res!(res);
+        handle_res!(res, "bladerf_set_loopback");
     }
 
     /// Fetch loopback state
-    pub fn get_loopback(&self) -> Result<BladeRFLoopback, isize> {
+    pub fn get_loopback(&self) -> Result<BladeRFLoopback, BladeRfError> {
         let mut loopback = bladerf_loopback_BLADERF_LB_NONE;
 
         let res = unsafe { bladerf_get_loopback(self.device, &mut loopback) };
         if res < 0 {
-            return Err(res as isize);
+            return Err(BladeRfError::from_code(res, "bladerf_get_loopback"));
         }
 
         match BladeRFLoopback::try_from(loopback) {
             Ok(v) => Ok(v),
-            Err(_) => Err(-1),
+            Err(_) => Err(BladeRfError::new(
+                ErrorKind::Unexpected,
+                "bladerf_get_loopback",
+            )),
         }
     }
 
@@ -591,6 +1619,45 @@ impl BladeRF {
 
     // Corrections and Calibration
 
+    /// Set an IQ or DC offset correction value
+    pub fn set_correction(
+        &self,
+        channel: Channel,
+        correction: Correction,
+        value: i16,
+    ) -> Result<(), BladeRfError> {
+        let res = unsafe {
+            bladerf_set_correction(
+                self.device,
+                channel.into(),
+                correction as bladerf_correction,
+                value,
+            )
+        };
+
+        handle_res!(res, "bladerf_set_correction", channel = channel);
+    }
+
+    /// Fetch an IQ or DC offset correction value
+    pub fn get_correction(
+        &self,
+        channel: Channel,
+        correction: Correction,
+    ) -> Result<i16, BladeRfError> {
+        let mut value = 0;
+
+        let res = unsafe {
+            bladerf_get_correction(
+                self.device,
+                channel.into(),
+                correction as bladerf_correction,
+                &mut value,
+            )
+        };
+
+        handle_res!(res, "bladerf_get_correction", channel = channel, value);
+    }
+
     // Corrections and calibration
 
     // Expansion boards
@@ -603,24 +1670,138 @@ impl BladeRF {
 
     // Asynchronous data transmission and reception
 
+    /// Start an asynchronous RX stream on a dedicated thread
+    ///
+    /// `callback` is called with each filled buffer until it returns [`StreamAction::Stop`],
+    /// the stream fails or the returned [`AsyncStream`] is stopped. The sample type must
+    /// match the non-metadata `config.format`.
+    pub fn rx_async<T, F>(
+        &self,
+        config: StreamConfig,
+        callback: F,
+    ) -> Result<AsyncStream<'_>, BladeRfError>
+    where
+        T: Sample,
+        F: FnMut(&mut [T]) -> StreamAction + Send + 'static,
+    {
+        AsyncStream::start(self, Direction::Rx, config, callback)
+    }
+
+    /// Start an asynchronous TX stream on a dedicated thread
+    ///
+    /// `callback` fills each buffer to be transmitted until it returns [`StreamAction::Stop`],
+    /// the stream fails or the returned [`AsyncStream`] is stopped.
+    pub fn tx_async<T, F>(
+        &self,
+        config: StreamConfig,
+        callback: F,
+    ) -> Result<AsyncStream<'_>, BladeRfError>
+    where
+        T: Sample,
+        F: FnMut(&mut [T]) -> StreamAction + Send + 'static,
+    {
+        AsyncStream::start(self, Direction::Tx, config, callback)
+    }
+
+    /// Set the asynchronous stream timeout in milliseconds
+    pub fn set_stream_timeout(&self, dir: Direction, timeout_ms: u32) -> Result<(), BladeRfError> {
+        let res = unsafe {
+            bladerf_set_stream_timeout(self.device, dir as bladerf_direction, timeout_ms)
+        };
+
+        handle_res!(res, "bladerf_set_stream_timeout");
+    }
+
+    /// Fetch the asynchronous stream timeout in milliseconds
+    pub fn get_stream_timeout(&self, dir: Direction) -> Result<u32, BladeRfError> {
+        let mut timeout_ms = 0;
+        let res = unsafe {
+            bladerf_get_stream_timeout(self.device, dir as bladerf_direction, &mut timeout_ms)
+        };
+
+        handle_res!(res, "bladerf_get_stream_timeout", timeout_ms)
+    }
+
     // Synchronous data transmission and reception
 
-    pub fn sync_config(
+    /// Start a synchronous RX stream, enabling its channels until the guard is dropped
+    pub fn rx_stream(&self, config: StreamConfig) -> Result<RxStream<'_>, BladeRfError> {
+        RxStream::new(DeviceHandle::Borrowed(self), config)
+    }
+
+    /// Start a synchronous TX stream, enabling its channels until the guard is dropped
+    pub fn tx_stream(&self, config: StreamConfig) -> Result<TxStream<'_>, BladeRfError> {
+        TxStream::new(DeviceHandle::Borrowed(self), config)
+    }
+
+    /// Start a `futures` Stream of timestamped RX sample blocks
+    ///
+    /// Blocks are received on a worker thread and buffered in a queue of up to
+    /// `queue_len` blocks of `config.buffer_size` samples. Blocks that do not fit
+    /// are dropped and reported, see [`RxSampleStream::dropped`].
+    ///
+    /// The stream shares ownership of the device, so it can be moved to
+    /// another task or thread.
+    #[cfg(feature = "async")]
+    pub fn rx_sample_stream<T: Sample>(
+        self: &sync::Arc<Self>,
+        config: StreamConfig,
+        queue_len: usize,
+    ) -> Result<RxSampleStream<T>, BladeRfError> {
+        RxSampleStream::start(self.clone(), config, queue_len)
+    }
+
+    /// Start a `futures` Sink of TX sample blocks
+    ///
+    /// Blocks are transmitted on a worker thread, queueing up to `queue_len`
+    /// blocks before applying backpressure. As for
+    /// [`BladeRF::rx_sample_stream`], the sink shares ownership of the device.
+    #[cfg(feature = "async")]
+    pub fn tx_sample_sink<T: Sample>(
+        self: &sync::Arc<Self>,
+        config: StreamConfig,
+        queue_len: usize,
+    ) -> Result<TxSampleSink<T>, BladeRfError> {
+        TxSampleSink::start(self.clone(), config, queue_len)
+    }
+
+    /// Configure the sync interface for a new stream guard, claiming its direction
+    ///
+    /// Fails while another stream is active in the direction, so the format
+    /// never changes under a running stream. The claim is released if
+    /// libbladeRF rejects the configuration.
+    pub(crate) fn sync_config(
         &self,
-        layout: bladerf_channel_layout,
-        format: bladerf_format,
+        layout: ChannelLayout,
+        format: Format,
         num_buffers: u32,
         buffer_size: u32,
         num_transfers: Option<u32>,
         stream_timeout: u32,
-    ) -> Result<isize, isize> {
+    ) -> Result<(), BladeRfError> {
         let num_transfers = num_transfers.unwrap_or(4);
+        let dir = layout.direction();
+
+        // Held across the call so no transfer in this direction runs meanwhile
+        let mut current = self.lock_sync_format(dir);
+        if self.streams[dir as usize]
+            .compare_exchange(
+                false,
+                true,
+                sync::atomic::Ordering::SeqCst,
+                sync::atomic::Ordering::SeqCst,
+            )
+            .is_err()
+        {
+            return Err(BladeRfError::new(ErrorKind::Inval, "bladerf_sync_config")
+                .with_detail(format!("{} stream already active", dir)));
+        }
 
         let res = unsafe {
             bladerf_sync_config(
                 self.device,
-                layout,
-                format,
+                layout as bladerf_channel_layout,
+                format as bladerf_format,
                 num_buffers,
                 buffer_size,
                 num_transfers,
@@ -628,38 +1809,99 @@ impl BladeRF {
             )
         };
 
-        handle_res!(res);
+        // Buffers are checked against the format once it is known to be in use
+        *current = match res >= 0 {
+            true => SyncFormat::Configured(format),
+            false => SyncFormat::Unknown,
+        };
+        if res < 0 {
+            self.streams[dir as usize].store(false, sync::atomic::Ordering::SeqCst);
+        }
+
+        handle_res!(res, "bladerf_sync_config");
+    }
+
+    /// Lock the sync format of a direction
+    fn lock_sync_format(&self, dir: Direction) -> sync::MutexGuard<'_, SyncFormat> {
+        self.sync_formats[dir as usize]
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Lock the sync interface of a direction for a transfer
+    ///
+    /// Checks a stream is active and the buffer's sample type matches its
+    /// format. The guard is held across the transfer, so neither can change
+    /// before libbladeRF uses the buffer.
+    fn lock_sync<T: Sample>(
+        &self,
+        dir: Direction,
+        op: &'static str,
+    ) -> Result<sync::MutexGuard<'_, SyncFormat>, BladeRfError> {
+        let current = self.lock_sync_format(dir);
+        if !self.streams[dir as usize].load(sync::atomic::Ordering::SeqCst) {
+            return Err(BladeRfError::new(ErrorKind::Inval, op)
+                .with_detail(format!("no {} stream active", dir)));
+        }
+
+        let detail = match *current {
+            SyncFormat::Configured(format) => {
+                sample::check::<T>(format, op)?;
+                return Ok(current);
+            }
+            // An async stream is active in this direction
+            SyncFormat::Unconfigured => "sync interface is not configured",
+            // The previous format may still be in use, so no buffer is safe
+            SyncFormat::Unknown => "sync format unknown after a failed sync_config",
+        };
+        Err(BladeRfError::new(ErrorKind::Inval, op).with_detail(format!("{} {}", dir, detail)))
     }
 
-    pub fn get_timestamp(&self, dir: bladerf_direction) -> u64 {
+    pub fn get_timestamp(&self, dir: Direction) -> Result<u64, BladeRfError> {
         let mut value = 0u64;
-        unsafe { bladerf_get_timestamp(self.device, dir, &mut value as *mut u64) };
+        let res = unsafe {
+            bladerf_get_timestamp(
+                self.device,
+                dir as bladerf_direction,
+                &mut value as *mut u64,
+            )
+        };
 
-        value
+        handle_res!(res, "bladerf_get_timestamp", value)
     }
 
-    pub fn sync_tx_meta(
+    /// Transmit samples with metadata, requires a metadata format
+    ///
+    /// Requires an active [`TxStream`], prefer [`TxStream::write_meta`].
+    pub fn sync_tx_meta<T: Sample>(
         &self,
-        data: &[Complex<i16>],
-        meta: &mut bladerf_metadata,
+        data: &[T],
+        meta: &mut Metadata,
         stream_timeout: u32,
-    ) -> Result<isize, isize> {
+    ) -> Result<(), BladeRfError> {
+        let _sync = self.lock_sync::<T>(Direction::Tx, "bladerf_sync_tx")?;
         let data_ptr: *mut std::ffi::c_void = data.as_ptr() as *mut std::ffi::c_void;
+        let mut raw = bladerf_metadata::from(*meta);
 
         let res = unsafe {
             bladerf_sync_tx(
                 self.device,
                 data_ptr,
                 data.len() as u32,
-                meta as *mut bladerf_metadata,
+                &mut raw,
                 stream_timeout,
             )
         };
+        *meta = raw.into();
 
-        handle_res!(res);
+        handle_res!(res, "bladerf_sync_tx");
     }
 
-    pub fn sync_tx(&self, data: &[Complex<i16>], stream_timeout: u32) -> Result<isize, isize> {
+    /// Transmit samples, the sample type must match the configured format
+    ///
+    /// Requires an active [`TxStream`], prefer [`TxStream::write`].
+    pub fn sync_tx<T: Sample>(&self, data: &[T], stream_timeout: u32) -> Result<(), BladeRfError> {
+        let _sync = self.lock_sync::<T>(Direction::Tx, "bladerf_sync_tx")?;
         let data_ptr: *mut std::ffi::c_void = data.as_ptr() as *mut std::ffi::c_void;
 
         let res = unsafe {
@@ -672,31 +1914,45 @@ impl BladeRF {
             )
         };
 
-        handle_res!(res)
+        handle_res!(res, "bladerf_sync_tx")
     }
 
-    pub fn sync_rx_meta(
+    /// Receive samples with metadata, requires a metadata format
+    ///
+    /// Requires an active [`RxStream`], prefer [`RxStream::read_meta`].
+    pub fn sync_rx_meta<T: Sample>(
         &self,
-        data: &mut [Complex<i16>],
-        meta: &mut bladerf_metadata,
+        data: &mut [T],
+        meta: &mut Metadata,
         stream_timeout: u32,
-    ) -> Result<isize, isize> {
+    ) -> Result<(), BladeRfError> {
+        let _sync = self.lock_sync::<T>(Direction::Rx, "bladerf_sync_rx")?;
         let data_ptr: *mut std::ffi::c_void = data.as_ptr() as *mut std::ffi::c_void;
+        let mut raw = bladerf_metadata::from(*meta);
 
         let res = unsafe {
             bladerf_sync_rx(
                 self.device,
                 data_ptr,
                 data.len() as u32,
-                meta as *mut bladerf_metadata,
+                &mut raw,
                 stream_timeout,
             )
         };
+        *meta = raw.into();
 
-        handle_res!(res)
+        handle_res!(res, "bladerf_sync_rx")
     }
 
-    pub fn sync_rx(&self, data: &mut [Complex<i16>], stream_timeout: u32) -> Result<isize, isize> {
+    /// Receive samples, the sample type must match the configured format
+    ///
+    /// Requires an active [`RxStream`], prefer [`RxStream::read`].
+    pub fn sync_rx<T: Sample>(
+        &self,
+        data: &mut [T],
+        stream_timeout: u32,
+    ) -> Result<(), BladeRfError> {
+        let _sync = self.lock_sync::<T>(Direction::Rx, "bladerf_sync_rx")?;
         let data_ptr: *mut std::ffi::c_void = data.as_ptr() as *mut std::ffi::c_void;
 
         let res = unsafe {
@@ -709,66 +1965,453 @@ impl BladeRF {
             )
         };
 
-        handle_res!(res)
+        handle_res!(res, "bladerf_sync_rx")
+    }
+
+    /// Check an f32 conversion's scratch buffer, returning the part in use
+    fn f32_scratch<'s, T: Sample>(
+        &self,
+        dir: Direction,
+        len: usize,
+        scratch: &'s mut [T],
+        op: &'static str,
+    ) -> Result<&'s mut [T], BladeRfError> {
+        // Metadata formats carry headers the conversion cannot account for
+        if *self.lock_sync_format(dir) == SyncFormat::Configured(T::META_FORMAT) {
+            return Err(BladeRfError::new(ErrorKind::Inval, op)
+                .with_detail(format!("{:?} is not supported", T::META_FORMAT)));
+        }
+
+        let available = scratch.len();
+        scratch.get_mut(..len).ok_or_else(|| {
+            BladeRfError::new(ErrorKind::Inval, op).with_detail(format!(
+                "scratch buffer of {} samples is shorter than {}",
+                available, len
+            ))
+        })
+    }
+
+    /// Receive samples normalized to ±1.0, converting from the configured format
+    ///
+    /// Samples are received into `scratch`, which must match the configured
+    /// format and hold at least `data.len()` samples. Metadata formats are rejected.
+    pub fn sync_rx_f32<T>(
+        &self,
+        data: &mut [Complex<f32>],
+        scratch: &mut [T],
+        stream_timeout: u32,
+    ) -> Result<(), BladeRfError>
+    where
+        T: Sample + Convert<Complex<f32>>,
+    {
+        let buffer = self.f32_scratch(Direction::Rx, data.len(), scratch, "sync_rx_f32")?;
+        self.sync_rx(buffer, stream_timeout)?;
+        convert::convert(buffer, data);
+
+        Ok(())
+    }
+
+    /// Transmit samples normalized to ±1.0, converting to the configured format
+    ///
+    /// Values outside the valid range are saturated. As for
+    /// [`BladeRF::sync_rx_f32`], samples are converted into `scratch`.
+    pub fn sync_tx_f32<T>(
+        &self,
+        data: &[Complex<f32>],
+        scratch: &mut [T],
+        stream_timeout: u32,
+    ) -> Result<(), BladeRfError>
+    where
+        T: Sample,
+        Complex<f32>: Convert<T>,
+    {
+        let buffer = self.f32_scratch(Direction::Tx, data.len(), scratch, "sync_tx_f32")?;
+        convert::convert(data, buffer);
+        self.sync_tx(buffer, stream_timeout)
     }
 
     // Device loading and programming
 
-    pub fn load_fpga(&self, file: String) -> Result<isize, isize> {
-        let c_string = ffi::CString::new(file.into_bytes()).unwrap();
+    pub fn load_fpga(&self, file: String) -> Result<(), BladeRfError> {
+        let c_string = ffi::CString::new(file.into_bytes())
+            .map_err(|_| BladeRfError::new(ErrorKind::Inval, "bladerf_load_fpga"))?;
+
+        let res = unsafe { bladerf_load_fpga(self.device, c_string.as_ptr()) };
+
+        handle_res!(res, "bladerf_load_fpga")
+    }
+
+    /// Load an FPGA bitstream from a path, which need not be valid UTF-8 on unix
+    pub fn load_fpga_path(&self, path: &path::Path) -> Result<(), BladeRfError> {
+        let c_string = fpga::path_cstring(path, "bladerf_load_fpga")?;
 
         let res = unsafe { bladerf_load_fpga(self.device, c_string.as_ptr()) };
 
-        handle_res!(res)
+        handle_res!(res, "bladerf_load_fpga")
     }
 
-    /*
-    pub fn bladerf_get_bias_tee(dev: *mut bladerf,
-        module: bladerf_module,
-        enable: *mut bool) -> ::libc::c_int;
-    pub fn bladerf_set_bias_tee(dev: *mut bladerf,
-            module: bladerf_module,
-            enable: bool) -> ::libc::c_int;
-    */
+    /// Locate and load the bitstream for this FPGA if it is not already configured
+    ///
+    /// Returns the file loaded, or `None` if the FPGA was already configured
+    pub fn load_fpga_auto(
+        &self,
+        search: &FpgaSearch,
+    ) -> Result<Option<path::PathBuf>, BladeRfError> {
+        if self.is_fpga_configured()? {
+            return Ok(None);
+        }
+
+        let size = self.get_fpga_size()?;
+        let file = search.find(size)?;
+
+        self.load_fpga_path(&file)?;
+
+        Ok(Some(file))
+    }
 
-    pub fn get_bias_tee(&self, module: bladerf_module) -> Result<bool, isize> {
+    pub fn get_bias_tee(&self, channel: Channel) -> Result<bool, BladeRfError> {
         let mut value = false;
-        let res = unsafe { bladerf_get_bias_tee(self.device, module, &mut value) };
-        handle_res!(res, value)
+        let res = unsafe { bladerf_get_bias_tee(self.device, channel.into(), &mut value) };
+        handle_res!(res, "bladerf_get_bias_tee", channel = channel, value)
     }
 
-    pub fn set_bias_tee(&self, module: bladerf_module, enable: bool) -> Result<isize, isize> {
-        let res = unsafe { bladerf_set_bias_tee(self.device, module, enable) };
-        handle_res!(res)
+    pub fn set_bias_tee(&self, channel: Channel, enable: bool) -> Result<(), BladeRfError> {
+        let res = unsafe { bladerf_set_bias_tee(self.device, channel.into(), enable) };
+        handle_res!(res, "bladerf_set_bias_tee", channel = channel)
     }
 
     // Higher level control
-    pub fn configure_module(&self, module: BladeRFChannel, config: BladeRFModuleConfig) {
-        BladeRF::set_frequency(self, module, config.frequency).unwrap();
-        BladeRF::set_sample_rate(self, module as i32, config.sample_rate).unwrap();
-        BladeRF::set_bandwidth(self, module as i32, config.bandwidth).unwrap();
-        BladeRF::set_gain(self, module as i32, config.lna_gain).unwrap();
-
-        // unsure whether this is still required / doesn't sem correct
-        #[cfg(feature = "unimplemented")]
-        match module {
-            BladeRFChannel::RX0 => {
-                BladeRF::set_rxvga1(self, config.vga1).unwrap();
-                BladeRF::set_rxvga2(self, config.vga2).unwrap();
+
+    /// Configure a channel, returning the values the hardware accepted
+    ///
+    /// Every value is checked against the channel's ranges before anything is applied.
+    /// Gains are applied using the model for the detected board, see [`BladeRFModuleConfig`].
+    /// A failed setting does not stop the remaining settings, all failures are reported.
+    pub fn configure_module(
+        &self,
+        channel: Channel,
+        config: &BladeRFModuleConfig,
+    ) -> Result<BladeRFModuleConfig, ConfigError> {
+        let board = self.board()?;
+
+        let op = "BladeRF::configure_module";
+        let mut failures = vec![];
+        self.check_module(board, channel, config, &|_| true, &mut failures);
+        if !failures.is_empty() {
+            return Err(ConfigError::Settings { op, failures });
+        }
+
+        let actual = self.apply_module(board, channel, config, &|_| true, &mut failures);
+
+        match failures.is_empty() {
+            true => Ok(actual),
+            false => Err(ConfigError::Settings { op, failures }),
+        }
+    }
+
+    /// Configure the device, returning the values the hardware accepted
+    ///
+    /// See [`BladeRF::configure_module`], failures from every channel are reported.
+    pub fn configure(&self, config: &BladeRFConfig) -> Result<BladeRFConfig, ConfigError> {
+        self.configure_only("BladeRF::configure", config, &|_, _| true)
+    }
+
+    /// Apply only the settings that differ from the current state of the device
+    ///
+    /// Returns the configuration read back after applying the changes,
+    /// see [`BladeRFConfig::diff`] and [`BladeRF::configure`].
+    pub fn apply_config(&self, config: &BladeRFConfig) -> Result<BladeRFConfig, ConfigError> {
+        let current = self.current_config()?;
+
+        let changes = current.diff(config);
+        if changes.is_empty() {
+            return Ok(current);
+        }
+
+        let filter = |ch, s| changes.contains(&(ch, s));
+        self.configure_only("BladeRF::apply_config", config, &filter)?;
+
+        Ok(self.current_config()?)
+    }
+
+    /// Configure the device, applying only the settings accepted by `filter`
+    ///
+    /// Failures are reported against `op`.
+    fn configure_only(
+        &self,
+        op: &'static str,
+        config: &BladeRFConfig,
+        filter: &dyn Fn(Option<Channel>, Setting) -> bool,
+    ) -> Result<BladeRFConfig, ConfigError> {
+        let board = self.board()?;
+
+        let modules = [
+            (Channel::rx(0), Some(&config.rx)),
+            (Channel::tx(0), Some(&config.tx)),
+            (Channel::rx(1), config.rx2.as_ref()),
+            (Channel::tx(1), config.tx2.as_ref()),
+        ];
+
+        let mut failures = vec![];
+        for (channel, module) in modules {
+            if let Some(module) = module {
+                let filter = |s| filter(Some(channel), s);
+                self.check_module(board, channel, module, &filter, &mut failures);
+            }
+        }
+        if !failures.is_empty() {
+            return Err(ConfigError::Settings { op, failures });
+        }
+
+        let mut actual = BladeRFConfig::default();
+
+        // Tuning mode affects how the channels are tuned, so is applied first
+        if let Some(mode) = config
+            .tuning_mode
+            .filter(|_| filter(None, Setting::TuningMode))
+        {
+            let res = self
+                .set_tuning_mode(mode)
+                .and_then(|_| self.get_tuning_mode());
+            actual.tuning_mode = config::record(&mut failures, Setting::TuningMode, res);
+        }
+
+        let [rx, tx, rx2, tx2] = modules.map(|(ch, module)| {
+            let filter = |s| filter(Some(ch), s);
+            module.map(|m| self.apply_module(board, ch, m, &filter, &mut failures))
+        });
+        actual.rx = rx.unwrap_or_default();
+        actual.tx = tx.unwrap_or_default();
+        actual.rx2 = rx2;
+        actual.tx2 = tx2;
+
+        if let Some(mux) = config.rx_mux.filter(|_| filter(None, Setting::RxMux)) {
+            let res = self.set_rx_mux(mux).and_then(|_| self.get_rx_mux());
+            actual.rx_mux = config::record(&mut failures, Setting::RxMux, res);
+        }
+
+        if let Some(loopback) = config.loopback.filter(|_| filter(None, Setting::Loopback)) {
+            let res = self
+                .set_loopback(loopback)
+                .and_then(|_| self.get_loopback());
+            actual.loopback = config::record(&mut failures, Setting::Loopback, res);
+        }
+
+        match failures.is_empty() {
+            true => Ok(actual),
+            false => Err(ConfigError::Settings { op, failures }),
+        }
+    }
+
+    /// Read back the configuration of a channel
+    ///
+    /// Optional settings the board does not support are `None`.
+    pub fn current_module_config(
+        &self,
+        channel: Channel,
+    ) -> Result<BladeRFModuleConfig, BladeRfError> {
+        let mut current = BladeRFModuleConfig {
+            frequency: self.get_frequency(channel)?,
+            sample_rate: self.get_sample_rate(channel)?,
+            bandwidth: self.get_bandwidth(channel)?,
+            ..Default::default()
+        };
+
+        for (setting, target) in config::gain_model(self.board()?, channel.direction) {
+            let gain = match target {
+                GainTarget::Overall => self.get_gain(channel)?,
+                GainTarget::Stage(stage) => self.get_gain_stage(channel, stage)?,
+            };
+            current.set_gain(*setting, gain);
+        }
+
+        if channel.is_rx() {
+            current.gain_mode = config::supported(self.get_gain_mode(channel))?;
+        }
+        current.lpf_mode = config::supported(self.get_lpf_mode(channel))?;
+        current.bias_tee = config::supported(self.get_bias_tee(channel))?;
+        current.corrections = config::supported(self.get_corrections(channel))?;
+
+        Ok(current)
+    }
+
+    /// Read back the configuration of every channel and the device wide settings
+    ///
+    /// This reports what the hardware is doing, which may differ from the values
+    /// last requested. Optional settings the board does not support are `None`.
+    pub fn current_config(&self) -> Result<BladeRFConfig, BladeRfError> {
+        let caps = self.capabilities()?;
+
+        let second = |ch: Channel, count: usize| match count > 1 {
+            true => self.current_module_config(ch).map(Some),
+            false => Ok(None),
+        };
+
+        Ok(BladeRFConfig {
+            tx: self.current_module_config(Channel::tx(0))?,
+            rx: self.current_module_config(Channel::rx(0))?,
+            tx2: second(Channel::tx(1), caps.tx_channels)?,
+            rx2: second(Channel::rx(1), caps.rx_channels)?,
+            loopback: config::supported(self.get_loopback())?,
+            rx_mux: config::supported(self.get_rx_mux())?,
+            tuning_mode: config::supported(self.get_tuning_mode())?,
+        })
+    }
+
+    /// Read every correction value for a channel
+    fn get_corrections(&self, channel: Channel) -> Result<Corrections, BladeRfError> {
+        let mut corrections = Corrections::default();
+        for corr in Corrections::ALL {
+            corrections.set(corr, self.get_correction(channel, corr)?);
+        }
+        Ok(corrections)
+    }
+
+    /// Check the settings accepted by `filter` against the channel's ranges
+    fn check_module(
+        &self,
+        board: Board,
+        channel: Channel,
+        config: &BladeRFModuleConfig,
+        filter: &dyn Fn(Setting) -> bool,
+        failures: &mut Vec<(Setting, BladeRfError)>,
+    ) {
+        let op = "BladeRF::configure_module";
+        let mut check =
+            |setting: Setting, value: f64, unit: &str, r: Result<Range, BladeRfError>| {
+                if !filter(setting) {
+                    return;
+                }
+                let res =
+                    r.and_then(|r| range::check(&r, value, op, channel, setting.name(), unit));
+                config::record(failures, setting, res);
+            };
+
+        check(
+            Setting::Frequency,
+            config.frequency.0 as f64,
+            "Hz",
+            self.get_frequency_range(channel),
+        );
+        check(
+            Setting::SampleRate,
+            config.sample_rate.0 as f64,
+            "sps",
+            self.get_sample_rate_range(channel),
+        );
+        check(
+            Setting::Bandwidth,
+            config.bandwidth.0 as f64,
+            "Hz",
+            self.get_bandwidth_range(channel),
+        );
+        for (setting, target) in config::gain_model(board, channel.direction) {
+            let Some(gain) = config.gain(*setting).filter(|_| config.manual_gain()) else {
+                continue;
+            };
+            let range = match target {
+                GainTarget::Overall => self.get_gain_range(channel),
+                GainTarget::Stage(stage) => self.get_gain_stage_range(channel, stage),
+            };
+            check(*setting, gain.0 as f64, "dB", range);
+        }
+    }
+
+    /// Apply the settings accepted by `filter`, recording failures and reading back the accepted values
+    fn apply_module(
+        &self,
+        board: Board,
+        channel: Channel,
+        config: &BladeRFModuleConfig,
+        filter: &dyn Fn(Setting) -> bool,
+        failures: &mut Vec<(Setting, BladeRfError)>,
+    ) -> BladeRFModuleConfig {
+        let mut actual = config.clone();
+
+        if filter(Setting::Frequency) {
+            let res = self
+                .set_frequency(channel, config.frequency)
+                .and_then(|_| self.get_frequency(channel));
+            if let Some(f) = config::record(failures, Setting::Frequency, res) {
+                actual.frequency = f;
+            }
+        }
+
+        if filter(Setting::SampleRate) {
+            let res = self.set_sample_rate(channel, config.sample_rate);
+            if let Some(r) = config::record(failures, Setting::SampleRate, res) {
+                actual.sample_rate = r;
             }
-            BladeRFChannel::TX0 => {
-                BladeRF::set_txvga1(self, config.vga1).unwrap();
-                BladeRF::set_txvga2(self, config.vga2).unwrap();
+        }
+
+        if filter(Setting::Bandwidth) {
+            let res = self.set_bandwidth(channel, config.bandwidth);
+            if let Some(b) = config::record(failures, Setting::Bandwidth, res) {
+                actual.bandwidth = b;
+            }
+        }
+
+        if let Some(mode) = config.lpf_mode.filter(|_| filter(Setting::LpfMode)) {
+            let res = self
+                .set_lpf_mode(channel, mode)
+                .and_then(|_| self.get_lpf_mode(channel));
+            actual.lpf_mode = config::record(failures, Setting::LpfMode, res);
+        }
+
+        // Gains can only be set manually, so the gain mode is applied first and
+        // the gains are skipped when switching to automatic control
+        if let Some(mode) = config.gain_mode.filter(|_| filter(Setting::GainMode)) {
+            let res = self
+                .set_gain_mode(channel, mode)
+                .and_then(|_| self.get_gain_mode(channel));
+            actual.gain_mode = config::record(failures, Setting::GainMode, res);
+        }
+
+        for (setting, target) in config::gain_model(board, channel.direction) {
+            let Some(gain) = config.gain(*setting) else {
+                continue;
+            };
+            if !config.manual_gain() || !filter(*setting) {
+                continue;
             }
-            BladeRFChannel::RX1 => {
-                BladeRF::set_rxvga1(self, config.vga1).unwrap();
-                BladeRF::set_rxvga2(self, config.vga2).unwrap();
+            let res = match target {
+                GainTarget::Overall => self.set_gain(channel, gain),
+                GainTarget::Stage(stage) => self
+                    .set_gain_stage(channel, stage, gain)
+                    .and_then(|_| self.get_gain_stage(channel, stage)),
+            };
+            if let Some(g) = config::record(failures, *setting, res) {
+                actual.set_gain(*setting, g);
             }
-            BladeRFChannel::Tx1 => {
-                BladeRF::set_txvga1(self, config.vga1).unwrap();
-                BladeRF::set_txvga2(self, config.vga2).unwrap();
+        }
+
+        if let Some(enable) = config.bias_tee.filter(|_| filter(Setting::BiasTee)) {
+            let res = self
+                .set_bias_tee(channel, enable)
+                .and_then(|_| self.get_bias_tee(channel));
+            actual.bias_tee = config::record(failures, Setting::BiasTee, res);
+        }
+
+        if let Some(corrections) = config.corrections {
+            let mut applied = Corrections::default();
+            let mut ok = true;
+            for corr in Corrections::ALL {
+                if !filter(Setting::Correction(corr)) {
+                    applied.set(corr, corrections.get(corr));
+                    continue;
+                }
+                let res = self
+                    .set_correction(channel, corr, corrections.get(corr))
+                    .and_then(|_| self.get_correction(channel, corr));
+                match config::record(failures, Setting::Correction(corr), res) {
+                    Some(v) => applied.set(corr, v),
+                    None => ok = false,
+                }
             }
-        };
+            actual.corrections = ok.then_some(applied);
+        }
+
+        actual
     }
 }
 
@@ -777,7 +2420,7 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_list_devices() -> Result<(), isize> {
+    fn test_list_devices() -> Result<(), BladeRfError> {
         match BladeRF::get_device_list() {
             Ok(devices) => {
                 println!("Discovered {:?} devices", devices.len());
@@ -799,7 +2442,7 @@ mod tests {
     fn test_open_devinfo() {
         let devices = BladeRF::get_device_list().unwrap();
         assert!(!devices.is_empty());
-        let _device = BladeRF::open_with_devinfo(devices[0]).unwrap();
+        let _device = BladeRF::open_with_devinfo(&devices[0]).unwrap();
     }
 
     #[test]
@@ -859,26 +2502,267 @@ mod tests {
     fn test_set_freq() {
         let device = BladeRF::open(None).unwrap();
 
-        let freq: u64 = 915000000;
+        let freq = Hertz::mhz(915);
 
         // Set and check frequency
-        device.set_frequency(BladeRFChannel::Rx1, freq).unwrap();
-        let actual_freq = device.get_frequency(BladeRFChannel::Rx1).unwrap();
-        let diff = freq as i64 - actual_freq as i64;
+        device.set_frequency(Channel::rx(0), freq).unwrap();
+        let actual_freq = device.get_frequency(Channel::rx(0)).unwrap();
+        let diff = freq.0 as i64 - actual_freq.0 as i64;
         assert!(i64::abs(diff) < 10);
     }
 
+    #[test]
+    fn test_ranges() {
+        let device = BladeRF::open(None).unwrap();
+
+        let range = device.get_frequency_range(Channel::rx(0)).unwrap();
+        println!("RX frequency range: {}", range);
+        assert!(range.contains(915e6));
+
+        // Out of range values are rejected before reaching the hardware
+        device.set_range_validation(true);
+        let err = device
+            .set_frequency(Channel::rx(0), Hertz(range.max_value() as u64 + 1_000_000))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Range);
+        assert_eq!(err.channel(), Some(Channel::rx(0)));
+    }
+
+    #[test]
+    fn test_gain_modes() {
+        let device = BladeRF::open(None).unwrap();
+        let caps = device.capabilities().unwrap();
+
+        let modes = device.get_gain_modes(Channel::rx(0)).unwrap();
+        println!("RX gain modes: {:?}", modes);
+        assert!(modes.iter().all(|m| caps.supports_gain_mode(*m)));
+
+        let initial = device.get_gain_mode(Channel::rx(0)).unwrap();
+
+        device
+            .set_gain_mode(Channel::rx(0), GainMode::Manual)
+            .unwrap();
+        assert_eq!(
+            device.get_gain_mode(Channel::rx(0)).unwrap(),
+            GainMode::Manual
+        );
+
+        device.set_gain_mode(Channel::rx(0), initial).unwrap();
+    }
+
+    #[test]
+    fn test_gain() {
+        let device = BladeRF::open(None).unwrap();
+        let caps = device.capabilities().unwrap();
+
+        let channels = (0..caps.rx_channels as u8)
+            .map(Channel::rx)
+            .chain((0..caps.tx_channels as u8).map(Channel::tx));
+
+        for ch in channels {
+            let initial = device.get_gain(ch).unwrap();
+            let range = device.get_gain_range(ch).unwrap();
+
+            let target = Decibels(range.quantize(range.min_value() + 10.0) as i32);
+            let actual = device.set_gain(ch, target).unwrap();
+            assert_eq!(device.get_gain(ch).unwrap(), actual);
+            println!("{} gain: requested {}, applied {}", ch, target, actual);
+
+            device.set_gain(ch, initial).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_gain_stages() {
+        let device = BladeRF::open(None).unwrap();
+
+        let stages = device.get_gain_stages(Channel::rx(0)).unwrap();
+        assert!(!stages.is_empty());
+
+        for stage in &stages {
+            let range = device.get_gain_stage_range(Channel::rx(0), stage).unwrap();
+            let gain = device.get_gain_stage(Channel::rx(0), stage).unwrap();
+            println!("RX stage {}: {} (range {})", stage, gain, range);
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let device = BladeRF::open(None).unwrap();
+
+        let report = device.report().unwrap();
+        println!("Report: {:?}", report);
+    }
+
+    #[test]
+    fn test_board() {
+        let device = BladeRF::open(None).unwrap();
+
+        let board = device.board().unwrap();
+        println!("Board: {}", board);
+    }
+
+    #[test]
+    fn test_rx_stream() {
+        let device = BladeRF::open(None).unwrap();
+
+        let mut stream = device.rx_stream(StreamConfig::default()).unwrap();
+        assert!(device.rx_stream(StreamConfig::default()).is_err());
+
+        let mut samples = vec![Complex::new(0i16, 0); 8192];
+        stream.read(&mut samples).unwrap();
+        stream.close().unwrap();
+
+        // Closing the stream allows a new one to be started
+        let stream = device.rx_stream(StreamConfig::default()).unwrap();
+        drop(stream);
+    }
+
+    #[test]
+    fn test_rx_stream_sc8() {
+        let device = BladeRF::open(None).unwrap();
+
+        let config = StreamConfig {
+            format: Format::Sc8Q7,
+            ..Default::default()
+        };
+        let mut stream = device.rx_stream(config).unwrap();
+
+        let mut samples = vec![Complex::new(0i8, 0); 8192];
+        stream.read(&mut samples).unwrap();
+
+        // Buffers must match the stream format
+        let mut wide = vec![Complex::new(0i16, 0); 8192];
+        let e = stream.read(&mut wide).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Inval);
+    }
+
+    #[test]
+    fn test_rx_async() {
+        let device = BladeRF::open(None).unwrap();
+
+        let (tx, rx) = sync::mpsc::channel();
+        let mut count = 0;
+        let stream = device
+            .rx_async(
+                StreamConfig::default(),
+                move |samples: &mut [Complex<i16>]| {
+                    tx.send(samples.len()).unwrap();
+                    count += 1;
+                    match count < 16 {
+                        true => StreamAction::Continue,
+                        false => StreamAction::Stop,
+                    }
+                },
+            )
+            .unwrap();
+        assert!(device.rx_stream(StreamConfig::default()).is_err());
+
+        stream.wait().unwrap();
+        assert_eq!(rx.iter().count(), 16);
+
+        // Streams are stopped when dropped
+        let stream = device
+            .rx_async(StreamConfig::default(), |_: &mut [Complex<i16>]| {
+                StreamAction::Continue
+            })
+            .unwrap();
+        assert!(stream.is_running());
+        drop(stream);
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_rx_sample_stream() {
+        use futures::StreamExt;
+
+        let device = sync::Arc::new(BladeRF::open(None).unwrap());
+
+        let mut stream: RxSampleStream =
+            device.rx_sample_stream(StreamConfig::default(), 4).unwrap();
+        let blocks: Vec<_> = futures::executor::block_on((&mut stream).take(8).collect());
+
+        let mut last = None;
+        for block in blocks {
+            let block = block.unwrap();
+            assert_eq!(block.samples.len(), 8192);
+            if let Some(t) = last {
+                assert!(block.timestamp > t);
+            }
+            last = Some(block.timestamp);
+        }
+        drop(stream);
+
+        // The RX direction is released once the stream is dropped
+        drop(device.rx_stream(StreamConfig::default()).unwrap());
+    }
+
+    #[test]
+    fn test_current_config() {
+        let device = BladeRF::open(None).unwrap();
+        let caps = device.capabilities().unwrap();
+
+        let config = device.current_config().unwrap();
+        assert_eq!(config.rx2.is_some(), caps.rx_channels > 1);
+        assert_eq!(config.tx2.is_some(), caps.tx_channels > 1);
+        assert_eq!(
+            config.rx.frequency,
+            device.get_frequency(Channel::rx(0)).unwrap()
+        );
+        println!("{:#?}", config);
+
+        // Applying a snapshot leaves the device unchanged
+        let applied = device.configure(&config).unwrap();
+        assert_eq!(device.current_config().unwrap(), applied);
+    }
+
+    #[test]
+    fn test_configure_module() {
+        let device = BladeRF::open(None).unwrap();
+
+        let rx = BladeRFModuleConfig {
+            frequency: Hertz::mhz(915),
+            sample_rate: SampleRate::msps(2),
+            bandwidth: Bandwidth::khz(1500),
+            gain: Some(Decibels(30)),
+            ..Default::default()
+        };
+        let actual = device.configure_module(Channel::rx(0), &rx).unwrap();
+        assert_eq!(
+            device.get_frequency(Channel::rx(0)).unwrap(),
+            actual.frequency
+        );
+        println!("Requested {:?}, applied {:?}", rx, actual);
+
+        // Out of range values are rejected before anything is applied
+        let invalid = BladeRFModuleConfig {
+            frequency: Hertz(1),
+            ..rx
+        };
+        let err = device
+            .configure_module(Channel::rx(0), &invalid)
+            .unwrap_err();
+        assert_eq!(err.failures().len(), 1);
+        assert_eq!(err.failures()[0].0, Setting::Frequency);
+        assert_eq!(err.failures()[0].1.kind(), ErrorKind::Range);
+        assert_eq!(
+            device.get_frequency(Channel::rx(0)).unwrap(),
+            actual.frequency
+        );
+    }
+
     #[test]
     fn test_set_sampling() {
         let device = BladeRF::open(None).unwrap();
 
-        let sampling: bladerf_sampling = bladerf_sampling_BLADERF_SAMPLING_INTERNAL;
+        let sampling = Sampling::Internal;
+        let caps = device.capabilities().unwrap();
 
         // Set and check frequency
         match device.set_sampling(sampling) {
-            Ok(_) => (),
+            Ok(_) => assert!(caps.sampling_select),
             Err(err) => {
-                if err != -8 {
+                if err.kind() != ErrorKind::Unsupported || caps.sampling_select {
                     panic!(
                         "unexpected error of value when calling set_sampling {:?}",
                         err
@@ -0,0 +1,168 @@
+//! Stream metadata
+
+use std::ops::{BitOr, BitOrAssign};
+
+use bladerf_sys::*;
+
+/// Metadata flags controlling a transfer
+///
+/// wraps the BLADERF_META_FLAG_* bits
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct MetaFlags(u32);
+
+impl MetaFlags {
+    /// Start of a TX burst
+    pub const TX_BURST_START: Self = Self(BLADERF_META_FLAG_TX_BURST_START);
+    /// End of a TX burst, followed by zero samples
+    pub const TX_BURST_END: Self = Self(BLADERF_META_FLAG_TX_BURST_END);
+    /// Transmit the burst immediately rather than at the timestamp
+    pub const TX_NOW: Self = Self(BLADERF_META_FLAG_TX_NOW);
+    /// Use the timestamp to insert zeros within a burst
+    pub const TX_UPDATE_TIMESTAMP: Self = Self(BLADERF_META_FLAG_TX_UPDATE_TIMESTAMP);
+    /// Receive the next available samples rather than at the timestamp
+    pub const RX_NOW: Self = Self(BLADERF_META_FLAG_RX_NOW);
+    /// Samples were lost in the FPGA before reception
+    pub const RX_HW_UNDERFLOW: Self = Self(BLADERF_META_FLAG_RX_HW_UNDERFLOW);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Check whether all flags in `other` are set
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for MetaFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for MetaFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Status reported by libbladeRF for a transfer
+///
+/// wraps the BLADERF_META_STATUS_* bits
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct MetaStatus(u32);
+
+impl MetaStatus {
+    /// Samples were discarded because the host fell behind
+    pub const OVERRUN: Self = Self(BLADERF_META_STATUS_OVERRUN);
+    /// TX samples were not provided in time
+    pub const UNDERRUN: Self = Self(BLADERF_META_STATUS_UNDERRUN);
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Check whether all status bits in `other` are set
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+/// Transfer metadata for metadata stream formats
+///
+/// Owned equivalent of bladerf_metadata
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Metadata {
+    /// Timestamp of the first sample, in sample clock ticks
+    pub timestamp: u64,
+    pub flags: MetaFlags,
+    /// Status reported by libbladeRF, set on return
+    pub status: MetaStatus,
+    /// Number of samples transferred, set on return
+    pub actual_count: u32,
+}
+
+impl Metadata {
+    /// Metadata receiving the next available samples
+    pub fn rx_now() -> Self {
+        Self {
+            flags: MetaFlags::RX_NOW,
+            ..Default::default()
+        }
+    }
+
+    /// Metadata for a transfer at a timestamp
+    pub fn at(timestamp: u64, flags: MetaFlags) -> Self {
+        Self {
+            timestamp,
+            flags,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<bladerf_metadata> for Metadata {
+    fn from(m: bladerf_metadata) -> Self {
+        Self {
+            timestamp: m.timestamp,
+            flags: MetaFlags(m.flags),
+            status: MetaStatus(m.status),
+            actual_count: m.actual_count,
+        }
+    }
+}
+
+impl From<Metadata> for bladerf_metadata {
+    fn from(m: Metadata) -> Self {
+        Self {
+            timestamp: m.timestamp,
+            flags: m.flags.0,
+            status: m.status.0,
+            actual_count: m.actual_count,
+            reserved: [0; 32],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags() {
+        let mut flags = MetaFlags::TX_BURST_START;
+        flags |= MetaFlags::TX_NOW;
+        assert!(flags.contains(MetaFlags::TX_NOW));
+        assert!(!flags.contains(MetaFlags::TX_BURST_START | MetaFlags::TX_BURST_END));
+        assert_eq!(
+            flags.bits(),
+            BLADERF_META_FLAG_TX_BURST_START | BLADERF_META_FLAG_TX_NOW
+        );
+        assert!(MetaFlags::empty().contains(MetaFlags::empty()));
+    }
+
+    #[test]
+    fn test_conversion() {
+        let meta = Metadata::at(1234, MetaFlags::TX_BURST_START | MetaFlags::TX_BURST_END);
+        let raw = bladerf_metadata::from(meta);
+        assert_eq!(raw.timestamp, 1234);
+        assert_eq!(
+            raw.flags,
+            BLADERF_META_FLAG_TX_BURST_START | BLADERF_META_FLAG_TX_BURST_END
+        );
+
+        let mut raw = bladerf_metadata::from(Metadata::rx_now());
+        raw.status = BLADERF_META_STATUS_OVERRUN;
+        raw.actual_count = 100;
+        let meta = Metadata::from(raw);
+        assert!(meta.status.contains(MetaStatus::OVERRUN));
+        assert!(meta.flags.contains(MetaFlags::RX_NOW));
+        assert_eq!(meta.actual_count, 100);
+    }
+}
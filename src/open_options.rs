@@ -0,0 +1,170 @@
+//! Builder for opening and preparing devices in a reproducible way
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    library_version, set_log_verbosity, set_usb_reset_on_open, BladeRF, BladeRFConfig,
+    BladeRfError, DeviceSelector, FpgaSearch, LogLevel, MinVersions,
+};
+
+/// FPGA bitstream to load on open
+#[derive(Clone, Debug)]
+enum FpgaLoad {
+    File(PathBuf),
+    Search(FpgaSearch),
+}
+
+/// Options used to open a device
+///
+/// ```no_run
+/// use bladerf::{DeviceSelector, LogLevel, OpenOptions};
+///
+/// let device = OpenOptions::new()
+///     .selector(DeviceSelector::new().serial("a66ab3"))
+///     .log_level(LogLevel::Warning)
+///     .fpga_auto()
+///     .open()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct OpenOptions {
+    selector: Option<DeviceSelector>,
+    usb_reset: Option<bool>,
+    log_level: Option<LogLevel>,
+    fpga: Option<FpgaLoad>,
+    min_versions: MinVersions,
+    validate_ranges: bool,
+    config: Option<BladeRFConfig>,
+}
+
+impl OpenOptions {
+    /// Create options opening the first available device
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the first device matching the provided selector
+    pub fn selector(mut self, selector: DeviceSelector) -> Self {
+        self.selector = Some(selector);
+        self
+    }
+
+    /// Enable or disable USB reset on open
+    ///
+    /// This is a libbladeRF global, left unchanged unless set
+    pub fn usb_reset(mut self, enabled: bool) -> Self {
+        self.usb_reset = Some(enabled);
+        self
+    }
+
+    /// Set the libbladeRF log verbosity
+    ///
+    /// This is a libbladeRF global, left unchanged unless set
+    pub fn log_level(mut self, level: LogLevel) -> Self {
+        self.log_level = Some(level);
+        self
+    }
+
+    /// Load the provided FPGA bitstream if the FPGA is not already configured
+    pub fn fpga(mut self, path: impl AsRef<Path>) -> Self {
+        self.fpga = Some(FpgaLoad::File(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Search for and load the bitstream matching the board if the FPGA is not
+    /// already configured
+    pub fn fpga_search(mut self, search: FpgaSearch) -> Self {
+        self.fpga = Some(FpgaLoad::Search(search));
+        self
+    }
+
+    /// Search the default locations for the bitstream, see [`FpgaSearch`]
+    pub fn fpga_auto(self) -> Self {
+        self.fpga_search(FpgaSearch::default())
+    }
+
+    /// Require minimum firmware, FPGA and library versions
+    ///
+    /// The FPGA version is checked after any bitstream is loaded, and fails if
+    /// no FPGA is configured
+    pub fn min_versions(mut self, min: MinVersions) -> Self {
+        self.min_versions = min;
+        self
+    }
+
+    /// Enable range validation on the opened device, see [`BladeRF::set_range_validation`]
+    ///
+    /// The initial configuration is also validated when enabled
+    pub fn validate_ranges(mut self, enabled: bool) -> Self {
+        self.validate_ranges = enabled;
+        self
+    }
+
+    /// Apply an initial configuration once the device is open
+    pub fn config(mut self, config: BladeRFConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Open and prepare a device using these options
+    pub fn open(&self) -> Result<BladeRF, BladeRfError> {
+        self.open_with_fpga_file().map(|(d, _)| d)
+    }
+
+    /// Open and prepare a device, also returning the FPGA bitstream loaded
+    ///
+    /// The file is `None` when no bitstream was loaded (the FPGA was already configured)
+    pub fn open_with_fpga_file(&self) -> Result<(BladeRF, Option<PathBuf>), BladeRfError> {
+        if let Some(level) = self.log_level {
+            set_log_verbosity(level);
+        }
+        if let Some(enabled) = self.usb_reset {
+            set_usb_reset_on_open(enabled);
+        }
+
+        // Check the library before touching any devices
+        self.min_versions
+            .check(None, None, Some(&library_version()))?;
+
+        let device = match &self.selector {
+            Some(s) => BladeRF::open_with(s)?,
+            None => BladeRF::open(None)?,
+        };
+
+        let fpga_file = match &self.fpga {
+            Some(FpgaLoad::File(path)) if !device.is_fpga_configured()? => {
+                device.load_fpga_path(path)?;
+                Some(path.clone())
+            }
+            Some(FpgaLoad::Search(search)) => device.load_fpga_auto(search)?,
+            _ => None,
+        };
+
+        device.check_min_versions(&self.min_versions)?;
+        device.set_range_validation(self.validate_ranges);
+
+        if let Some(config) = &self.config {
+            device.configure(config)?;
+        }
+
+        Ok((device, fpga_file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorKind, Version};
+
+    #[test]
+    fn test_library_version_checked_first() {
+        let min = MinVersions::new().library(Version::new(u16::MAX, 0, 0));
+
+        let err = match OpenOptions::new().min_versions(min).open() {
+            Ok(_) => panic!("expected library version check to fail"),
+            Err(e) => e,
+        };
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        assert!(err.detail().unwrap().contains("libbladeRF"));
+    }
+}
@@ -0,0 +1,155 @@
+//! Hardware parameter ranges
+
+use std::fmt::Display;
+
+use bladerf_sys::*;
+
+use crate::{BladeRfError, Channel, ErrorKind};
+
+/// Range of valid values for a parameter
+///
+/// Equivalent to bladerf_range, values are `min`, `max` and `step` multiplied by `scale`.
+/// The helpers operate on scaled values.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Range {
+    pub min: i64,
+    pub max: i64,
+    pub step: i64,
+    pub scale: f32,
+}
+
+impl Range {
+    /// Scaled minimum value
+    pub fn min_value(&self) -> f64 {
+        self.min as f64 * self.scale as f64
+    }
+
+    /// Scaled maximum value
+    pub fn max_value(&self) -> f64 {
+        self.max as f64 * self.scale as f64
+    }
+
+    /// Scaled step between values, zero when the range is continuous
+    pub fn step_value(&self) -> f64 {
+        self.step as f64 * self.scale as f64
+    }
+
+    /// Check whether a value lies within the range
+    pub fn contains(&self, value: f64) -> bool {
+        value >= self.min_value() && value <= self.max_value()
+    }
+
+    /// Limit a value to the range
+    pub fn clamp(&self, value: f64) -> f64 {
+        value.clamp(self.min_value(), self.max_value())
+    }
+
+    /// Limit a value to the range and round it to the nearest step
+    pub fn quantize(&self, value: f64) -> f64 {
+        let value = self.clamp(value);
+        let step = self.step_value();
+        if step <= 0.0 {
+            return value;
+        }
+
+        let min = self.min_value();
+        let quantized = min + ((value - min) / step).round() * step;
+
+        // Rounding up may overshoot a max that is not a whole number of steps
+        if quantized > self.max_value() {
+            quantized - step
+        } else {
+            quantized
+        }
+    }
+}
+
+/// Check a value lies within a range, describing the failure otherwise
+pub(crate) fn check(
+    range: &Range,
+    value: f64,
+    op: &'static str,
+    channel: Channel,
+    name: &str,
+    unit: &str,
+) -> Result<(), BladeRfError> {
+    if range.contains(value) {
+        return Ok(());
+    }
+
+    Err(BladeRfError::new(ErrorKind::Range, op)
+        .with_channel(channel)
+        .with_detail(format!(
+            "{} {} {} out of range {} {}",
+            name, value, unit, range, unit
+        )))
+}
+
+impl From<&bladerf_range> for Range {
+    fn from(r: &bladerf_range) -> Self {
+        Self {
+            min: r.min,
+            max: r.max,
+            step: r.step,
+            scale: r.scale,
+        }
+    }
+}
+
+impl Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}, {}]", self.min_value(), self.max_value())?;
+        if self.step > 0 {
+            write!(f, " step {}", self.step_value())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(min: i64, max: i64, step: i64, scale: f32) -> Range {
+        Range {
+            min,
+            max,
+            step,
+            scale,
+        }
+    }
+
+    #[test]
+    fn test_contains_clamp() {
+        let r = range(70_000_000, 6_000_000_000, 2, 1.0);
+
+        assert!(r.contains(915e6));
+        assert!(r.contains(70e6));
+        assert!(!r.contains(50e6));
+        assert_eq!(r.clamp(50e6), 70e6);
+        assert_eq!(r.clamp(7e9), 6e9);
+    }
+
+    #[test]
+    fn test_quantize() {
+        let r = range(-15, 60, 1, 1.0);
+        assert_eq!(r.quantize(10.4), 10.0);
+        assert_eq!(r.quantize(10.6), 11.0);
+        assert_eq!(r.quantize(80.0), 60.0);
+
+        let r = range(0, 10, 4, 0.5);
+        assert_eq!(r.max_value(), 5.0);
+        assert_eq!(r.quantize(1.1), 2.0);
+        assert_eq!(r.quantize(4.9), 4.0);
+
+        let continuous = range(0, 100, 0, 1.0);
+        assert_eq!(continuous.quantize(12.34), 12.34);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(range(-15, 60, 1, 1.0).to_string(), "[-15, 60] step 1");
+        assert_eq!(range(0, 10, 0, 0.5).to_string(), "[0, 5]");
+    }
+}
@@ -0,0 +1,60 @@
+//! Device identity and version reporting
+
+use crate::{Board, FpgaSize, FpgaSource, UsbSpeed, Version};
+
+/// Identity and version information for an open device
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceReport {
+    pub serial: String,
+    pub board: Board,
+    pub usb_speed: UsbSpeed,
+    pub firmware: Version,
+    /// FPGA version, unavailable when the FPGA is not configured
+    pub fpga: Option<Version>,
+    pub fpga_configured: bool,
+    pub fpga_size: FpgaSize,
+    pub fpga_source: FpgaSource,
+    /// Flash size in bytes
+    pub flash_size: u32,
+    /// Whether the flash size was inferred from the FPGA size rather than read
+    pub flash_size_guessed: bool,
+    /// libbladeRF version
+    pub library: Version,
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serde() {
+        let version = Version {
+            major: 2,
+            minor: 4,
+            patch: 0,
+            describe: "2.4.0".to_string(),
+        };
+        let report = DeviceReport {
+            serial: "a66ab3".to_string(),
+            board: Board::BladeRf2,
+            usb_speed: UsbSpeed::Super,
+            firmware: version.clone(),
+            fpga: None,
+            fpga_configured: false,
+            fpga_size: FpgaSize::A4,
+            fpga_source: FpgaSource::Unknown,
+            flash_size: 4 << 20,
+            flash_size_guessed: false,
+            library: version,
+        };
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["usb_speed"], "super");
+        assert_eq!(json["fpga_size"], "a4");
+        assert_eq!(json["fpga_source"], "unknown");
+
+        let parsed: DeviceReport = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, report);
+    }
+}
@@ -0,0 +1,75 @@
+//! Sample types for streaming
+
+use num_complex::Complex;
+
+use crate::{BladeRfError, ErrorKind, Format};
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for super::Complex<i16> {}
+    impl Sealed for super::Complex<i8> {}
+}
+
+/// Sample type transferred by the streaming interfaces
+///
+/// Implemented for `Complex<i16>` (SC16 Q11) and `Complex<i8>` (SC8 Q7).
+/// SC8 Q7 halves the USB bandwidth per sample, allowing higher sample rates
+/// over USB 2 at reduced resolution. It requires a recent FPGA and libbladeRF.
+pub trait Sample: Copy + Default + Send + Sync + 'static + private::Sealed {
+    /// Sample format without metadata
+    const FORMAT: Format;
+    /// Sample format with metadata
+    const META_FORMAT: Format;
+    /// Integer type of the I and Q components
+    type Component: Copy;
+
+    /// Check whether buffers of this type can be used with a format
+    fn supports(format: Format) -> bool {
+        format == Self::FORMAT || format == Self::META_FORMAT
+    }
+}
+
+impl Sample for Complex<i16> {
+    const FORMAT: Format = Format::Sc16Q11;
+    const META_FORMAT: Format = Format::Sc16Q11Meta;
+    type Component = i16;
+}
+
+impl Sample for Complex<i8> {
+    const FORMAT: Format = Format::Sc8Q7;
+    const META_FORMAT: Format = Format::Sc8Q7Meta;
+    type Component = i8;
+}
+
+/// Check a buffer's sample type matches a stream format
+pub(crate) fn check<T: Sample>(format: Format, op: &'static str) -> Result<(), BladeRfError> {
+    if T::supports(format) {
+        return Ok(());
+    }
+
+    Err(BladeRfError::new(ErrorKind::Inval, op).with_detail(format!(
+        "{:?} buffer does not match {:?} stream format",
+        T::FORMAT,
+        format
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        assert!(check::<Complex<i16>>(Format::Sc16Q11, "op").is_ok());
+        assert!(check::<Complex<i16>>(Format::Sc16Q11Meta, "op").is_ok());
+        assert!(check::<Complex<i8>>(Format::Sc8Q7Meta, "op").is_ok());
+
+        let e = check::<Complex<i8>>(Format::Sc16Q11, "op").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Inval);
+
+        assert!(check::<Complex<i16>>(Format::Sc8Q7, "op").is_err());
+        assert!(check::<Complex<i16>>(Format::PacketMeta, "op").is_err());
+        assert!(check::<Complex<i8>>(Format::PacketMeta, "op").is_err());
+    }
+}
@@ -0,0 +1,434 @@
+//! `futures` Stream and Sink adapters over the sync interface
+//!
+//! A worker thread owns the blocking libbladeRF sync calls and exchanges
+//! samples with the async side through a bounded queue. The adapters do not
+//! depend on a particular runtime, so can be polled from tokio tasks directly
+//! rather than wrapping `sync_rx` / `sync_tx` in `spawn_blocking`.
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::thread;
+
+use futures::channel::mpsc;
+use futures::{Sink, Stream, StreamExt};
+use num_complex::Complex;
+
+use crate::async_stream::Shared;
+use crate::stream::DeviceHandle;
+use crate::{
+    sample, BladeRF, BladeRfError, ErrorKind, MetaStatus, Metadata, RxStream, Sample, StreamConfig,
+    TxStream,
+};
+
+/// Block of received samples
+#[derive(Clone, PartialEq, Debug)]
+pub struct SampleBlock<T = Complex<i16>> {
+    /// Timestamp of the first sample, in sample clock ticks
+    pub timestamp: u64,
+    pub samples: Vec<T>,
+    /// Blocks dropped since the previous block because the consumer fell behind
+    pub dropped: u64,
+    /// Samples were lost before this block by the device or libbladeRF
+    pub overrun: bool,
+}
+
+/// Error raised by a worker thread, reported once the queue has drained
+type WorkerError = Arc<Mutex<Option<BladeRfError>>>;
+
+fn fail(error: &WorkerError, e: BladeRfError) -> Result<(), BladeRfError> {
+    *error.lock().unwrap_or_else(|e| e.into_inner()) = Some(e.clone());
+    Err(e)
+}
+
+fn take_error(error: &WorkerError) -> Option<BladeRfError> {
+    error.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+fn spawn(
+    name: &str,
+    worker: impl FnOnce() -> Result<(), BladeRfError> + Send + 'static,
+) -> Result<thread::JoinHandle<Result<(), BladeRfError>>, BladeRfError> {
+    thread::Builder::new()
+        .name(name.to_string())
+        .spawn(worker)
+        .map_err(|e| {
+            BladeRfError::new(ErrorKind::Unexpected, "thread::spawn").with_detail(e.to_string())
+        })
+}
+
+/// Queue a block without blocking, counting blocks dropped while the queue is full
+///
+/// Returns false once the consumer has gone.
+fn push<T>(
+    queue: &mut mpsc::Sender<SampleBlock<T>>,
+    mut block: SampleBlock<T>,
+    dropped: &mut u64,
+    total: &AtomicU64,
+) -> bool {
+    block.dropped = *dropped;
+    match queue.try_send(block) {
+        Ok(()) => {
+            *dropped = 0;
+            true
+        }
+        Err(e) if e.is_full() => {
+            *dropped += 1;
+            total.fetch_add(1, Ordering::Relaxed);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Bounded channel holding `len` items with a single sender
+fn channel<T>(len: usize) -> (mpsc::Sender<T>, mpsc::Receiver<T>) {
+    // Each sender is guaranteed one slot on top of the buffer
+    mpsc::channel(len.max(1) - 1)
+}
+
+fn rx_worker<T: Sample>(
+    device: Arc<BladeRF>,
+    config: StreamConfig,
+    mut queue: mpsc::Sender<SampleBlock<T>>,
+    shared: Arc<Shared>,
+    total: Arc<AtomicU64>,
+    error: WorkerError,
+) -> Result<(), BladeRfError> {
+    let len = config.buffer_size as usize;
+    let mut dropped = 0;
+
+    while !shared.stopped() {
+        let mut samples = vec![T::default(); len];
+        let mut meta = Metadata::rx_now();
+
+        // Checked against the stream format under the sync lock
+        if let Err(e) = device.sync_rx_meta(&mut samples, &mut meta, config.timeout_ms) {
+            return fail(&error, e);
+        }
+
+        samples.truncate(meta.actual_count as usize);
+        let block = SampleBlock {
+            timestamp: meta.timestamp,
+            samples,
+            dropped: 0,
+            overrun: meta.status.contains(MetaStatus::OVERRUN),
+        };
+        if !push(&mut queue, block, &mut dropped, &total) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn tx_worker<T: Sample>(
+    device: Arc<BladeRF>,
+    config: StreamConfig,
+    mut queue: mpsc::Receiver<Vec<T>>,
+    shared: Arc<Shared>,
+    error: WorkerError,
+) -> Result<(), BladeRfError> {
+    // Wait for the next block, or for a stop request
+    let mut next = || {
+        futures::executor::block_on(futures::future::poll_fn(|cx| {
+            shared.register(cx.waker());
+            match shared.stopped() {
+                true => Poll::Ready(None),
+                false => queue.poll_next_unpin(cx),
+            }
+        }))
+    };
+
+    while let Some(samples) = next() {
+        if let Err(e) = device.sync_tx(&samples, config.timeout_ms) {
+            queue.close();
+            return fail(&error, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Receiving half of the RX queue, reporting any worker error once drained
+struct BlockQueue<T> {
+    queue: mpsc::Receiver<SampleBlock<T>>,
+    error: WorkerError,
+}
+
+impl<T> BlockQueue<T> {
+    fn poll_next(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<SampleBlock<T>, BladeRfError>>> {
+        match self.queue.poll_next_unpin(cx) {
+            Poll::Ready(Some(block)) => Poll::Ready(Some(Ok(block))),
+            Poll::Ready(None) => Poll::Ready(take_error(&self.error).map(Err)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Asynchronous stream of received sample blocks, see [`BladeRF::rx_sample_stream`]
+///
+/// Blocks are dropped when the queue is full rather than stalling the device,
+/// and are reported through [`SampleBlock::dropped`]. The stream ends after
+/// yielding an error.
+pub struct RxSampleStream<T: Sample = Complex<i16>> {
+    queue: BlockQueue<T>,
+    shared: Arc<Shared>,
+    dropped: Arc<AtomicU64>,
+    // Dropped after the worker has been joined, disabling the channels
+    stream: RxStream<'static>,
+}
+
+impl<T: Sample> RxSampleStream<T> {
+    pub(crate) fn start(
+        device: Arc<BladeRF>,
+        mut config: StreamConfig,
+        queue_len: usize,
+    ) -> Result<Self, BladeRfError> {
+        // Timestamps are only reported with metadata
+        sample::check::<T>(config.format, "RxSampleStream::start")?;
+        config.format = T::META_FORMAT;
+
+        let stream = RxStream::new(DeviceHandle::Shared(device.clone()), config.clone())?;
+        let (tx, rx) = channel(queue_len);
+        let shared = Shared::new();
+        let dropped = Arc::new(AtomicU64::new(0));
+        let error = WorkerError::default();
+
+        let worker = {
+            let (shared, dropped, error) = (shared.clone(), dropped.clone(), error.clone());
+            move || rx_worker(device, config, tx, shared, dropped, error)
+        };
+        shared.track(stream.device(), spawn("bladerf-rx-sync", worker)?);
+
+        Ok(Self {
+            queue: BlockQueue { queue: rx, error },
+            shared,
+            dropped,
+            stream,
+        })
+    }
+
+    /// Total blocks dropped because the consumer fell behind
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<T: Sample> Stream for RxSampleStream<T> {
+    type Item = Result<SampleBlock<T>, BladeRfError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().queue.poll_next(cx)
+    }
+}
+
+impl<T: Sample> Drop for RxSampleStream<T> {
+    fn drop(&mut self) {
+        self.queue.queue.close();
+        let _ = self.shared.join(true);
+        self.shared.untrack(self.stream.device());
+    }
+}
+
+/// Asynchronous sink transmitting sample blocks, see [`BladeRF::tx_sample_sink`]
+///
+/// The bounded queue applies backpressure rather than dropping samples.
+/// Dropping the sink waits for queued blocks to be transmitted.
+pub struct TxSampleSink<T: Sample = Complex<i16>> {
+    queue: mpsc::Sender<Vec<T>>,
+    shared: Arc<Shared>,
+    error: WorkerError,
+    channels: usize,
+    // Dropped after the worker has been joined, disabling the channels
+    stream: TxStream<'static>,
+}
+
+impl<T: Sample> TxSampleSink<T> {
+    pub(crate) fn start(
+        device: Arc<BladeRF>,
+        config: StreamConfig,
+        queue_len: usize,
+    ) -> Result<Self, BladeRfError> {
+        let op = "TxSampleSink::start";
+        if config.format != T::FORMAT {
+            sample::check::<T>(config.format, op)?;
+            return Err(BladeRfError::new(ErrorKind::Unsupported, op)
+                .with_detail(format!("unsupported format {:?}", config.format)));
+        }
+
+        let stream = TxStream::new(DeviceHandle::Shared(device.clone()), config.clone())?;
+        let channels = stream.layout().num_channels();
+        let (tx, rx) = channel(queue_len);
+        let shared = Shared::new();
+        let error = WorkerError::default();
+
+        let worker = {
+            let (shared, error) = (shared.clone(), error.clone());
+            move || tx_worker(device, config, rx, shared, error)
+        };
+        shared.track(stream.device(), spawn("bladerf-tx-sync", worker)?);
+
+        Ok(Self {
+            queue: tx,
+            shared,
+            error,
+            channels,
+            stream,
+        })
+    }
+
+    /// Error for a closed queue, the worker's error if it failed
+    fn closed(&self) -> BladeRfError {
+        take_error(&self.error).unwrap_or_else(|| {
+            BladeRfError::new(ErrorKind::Unexpected, "TxSampleSink").with_detail("stream closed")
+        })
+    }
+}
+
+impl<T: Sample> Sink<Vec<T>> for TxSampleSink<T> {
+    type Error = BladeRfError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.queue.poll_ready(cx).map_err(|_| this.closed())
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Vec<T>) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        if item.len() % this.channels != 0 {
+            return Err(
+                BladeRfError::new(ErrorKind::Inval, "TxSampleSink::start_send").with_detail(
+                    format!(
+                        "buffer length {} is not a multiple of {} channels",
+                        item.len(),
+                        this.channels
+                    ),
+                ),
+            );
+        }
+        this.queue.start_send(item).map_err(|_| this.closed())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        Pin::new(&mut this.queue)
+            .poll_flush(cx)
+            .map_err(|_| this.closed())
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        Pin::new(&mut this.queue)
+            .poll_close(cx)
+            .map_err(|_| this.closed())
+    }
+}
+
+impl<T: Sample> Drop for TxSampleSink<T> {
+    fn drop(&mut self) {
+        // Closing the queue lets the worker drain it before exiting
+        self.queue.close_channel();
+        let _ = self.shared.join(false);
+        self.shared.untrack(self.stream.device());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+
+    fn block(timestamp: u64) -> SampleBlock {
+        SampleBlock {
+            timestamp,
+            samples: vec![Complex::new(1, 1); 4],
+            dropped: 0,
+            overrun: false,
+        }
+    }
+
+    #[test]
+    fn test_overflow() {
+        let (mut tx, mut rx) = channel(2);
+        let total = AtomicU64::new(0);
+        let mut dropped = 0;
+
+        for t in 0..5 {
+            assert!(push(&mut tx, block(t), &mut dropped, &total));
+        }
+        assert_eq!(total.load(Ordering::Relaxed), 3);
+
+        assert_eq!(block_on(rx.next()).unwrap().timestamp, 0);
+        assert_eq!(block_on(rx.next()).unwrap().timestamp, 1);
+
+        // The next queued block reports the gap
+        assert!(push(&mut tx, block(5), &mut dropped, &total));
+        let b = block_on(rx.next()).unwrap();
+        assert_eq!((b.timestamp, b.dropped), (5, 3));
+
+        drop(rx);
+        assert!(!push(&mut tx, block(6), &mut dropped, &total));
+    }
+
+    #[test]
+    fn test_error_after_drain() {
+        let (mut tx, rx) = channel(4);
+        let error = WorkerError::default();
+        let mut queue = BlockQueue {
+            queue: rx,
+            error: error.clone(),
+        };
+
+        tx.try_send(block(0)).unwrap();
+        let _ = fail(
+            &error,
+            BladeRfError::new(ErrorKind::Timeout, "bladerf_sync_rx"),
+        );
+        drop(tx);
+
+        let mut next = || block_on(futures::future::poll_fn(|cx| queue.poll_next(cx)));
+        assert_eq!(next().unwrap().unwrap().timestamp, 0);
+        assert_eq!(next().unwrap().unwrap_err().kind(), ErrorKind::Timeout);
+        assert!(next().is_none());
+    }
+
+    fn assert_send<T: Send + 'static>() {}
+
+    #[test]
+    fn test_send() {
+        // The adapters can be moved into spawned tasks
+        assert_send::<RxSampleStream>();
+        assert_send::<TxSampleSink<Complex<i8>>>();
+        assert_send::<Arc<BladeRF>>();
+    }
+
+    #[test]
+    fn test_stop_wakes_worker() {
+        let (_tx, mut rx) = channel::<u32>(1);
+        let shared = Shared::new();
+
+        let waiter = {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                block_on(futures::future::poll_fn(|cx| {
+                    shared.register(cx.waker());
+                    match shared.stopped() {
+                        true => Poll::Ready(None),
+                        false => rx.poll_next_unpin(cx),
+                    }
+                }))
+            })
+        };
+
+        // Stopping wakes a worker blocked on an empty queue
+        thread::sleep(std::time::Duration::from_millis(10));
+        assert!(shared.join(true).is_none());
+        assert_eq!(waiter.join().unwrap(), None);
+    }
+}
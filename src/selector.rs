@@ -0,0 +1,264 @@
+//! Device selection using libbladeRF device identifier strings
+//!
+//! Identifiers take the form `<backend>:[device=<bus>:<addr>] [instance=<n>] [serial=<serial>]`,
+//! see <https://nuand.com/libbladeRF-doc/v2.5.0/group___f_n___i_n_i_t.html>
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{Backend, BladeRfError, DeviceInfo, ErrorKind};
+
+/// Maximum length of a device serial number
+const SERIAL_MAX_LEN: usize = 32;
+
+/// Backends that may be named in an identifier
+const BACKENDS: [Backend; 4] = [
+    Backend::Linux,
+    Backend::Libusb,
+    Backend::Cypress,
+    Backend::Dummy,
+];
+
+/// Selector for opening or matching devices
+///
+/// Unset fields match any device, a serial matches on prefix.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct DeviceSelector {
+    backend: Option<Backend>,
+    serial: Option<String>,
+    device: Option<(u8, u8)>,
+    instance: Option<u32>,
+}
+
+impl DeviceSelector {
+    /// Create a selector matching any device
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Select devices using the specified backend
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = match backend {
+            Backend::Any => None,
+            b => Some(b),
+        };
+        self
+    }
+
+    /// Select devices whose serial number starts with `serial` (case insensitive)
+    pub fn serial(mut self, serial: impl Into<String>) -> Self {
+        self.serial = Some(serial.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Select the device at the specified USB bus and address
+    pub fn device(mut self, usb_bus: u8, usb_addr: u8) -> Self {
+        self.device = Some((usb_bus, usb_addr));
+        self
+    }
+
+    /// Select the device with the specified instance number
+    pub fn instance(mut self, instance: u32) -> Self {
+        self.instance = Some(instance);
+        self
+    }
+
+    /// Check whether a device matches this selector
+    ///
+    /// Equivalent to bladerf_devinfo_matches / bladerf_devstr_matches
+    pub fn matches(&self, info: &DeviceInfo) -> bool {
+        if let Some(b) = self.backend {
+            if b != info.backend {
+                return false;
+            }
+        }
+
+        if let Some(s) = &self.serial {
+            if !info.serial.to_ascii_lowercase().starts_with(s.as_str()) {
+                return false;
+            }
+        }
+
+        if let Some((bus, addr)) = self.device {
+            if bus != info.usb_bus || addr != info.usb_addr {
+                return false;
+            }
+        }
+
+        if let Some(i) = self.instance {
+            if i != info.instance {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl From<&DeviceInfo> for DeviceSelector {
+    /// Build a selector matching exactly the provided device
+    fn from(info: &DeviceInfo) -> Self {
+        Self::new()
+            .backend(info.backend)
+            .device(info.usb_bus, info.usb_addr)
+            .instance(info.instance)
+            .serial(&info.serial)
+    }
+}
+
+impl Display for DeviceSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.backend.unwrap_or(Backend::Any))?;
+
+        let mut options = vec![];
+        if let Some((bus, addr)) = self.device {
+            options.push(format!("device={}:{}", bus, addr));
+        }
+        if let Some(i) = self.instance {
+            options.push(format!("instance={}", i));
+        }
+        if let Some(s) = &self.serial {
+            options.push(format!("serial={}", s));
+        }
+
+        write!(f, "{}", options.join(" "))
+    }
+}
+
+impl FromStr for DeviceSelector {
+    type Err = BladeRfError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = |detail: String| {
+            BladeRfError::new(ErrorKind::Inval, "DeviceSelector::from_str").with_detail(detail)
+        };
+
+        let s = s.trim();
+        let (backend, options) = s.split_once(':').unwrap_or((s, ""));
+
+        let mut selector = Self::new();
+
+        selector.backend = match backend {
+            "" | "*" => None,
+            b => match BACKENDS.iter().find(|v| v.as_str() == b) {
+                Some(v) => Some(*v),
+                None => return Err(err(format!("unknown backend '{}'", b))),
+            },
+        };
+
+        for option in options.split_whitespace() {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| err(format!("expected <key>=<value>, found '{}'", option)))?;
+
+            if value.is_empty() {
+                return Err(err(format!("missing value for '{}'", key)));
+            }
+
+            match key {
+                "device" => {
+                    let (bus, addr) = value.split_once(':').ok_or_else(|| {
+                        err(format!("expected device=<bus>:<addr>, found '{}'", value))
+                    })?;
+                    let bus = bus
+                        .parse()
+                        .map_err(|_| err(format!("invalid USB bus '{}'", bus)))?;
+                    let addr = addr
+                        .parse()
+                        .map_err(|_| err(format!("invalid USB address '{}'", addr)))?;
+                    selector.device = Some((bus, addr));
+                }
+                "instance" => {
+                    let instance = value
+                        .parse()
+                        .map_err(|_| err(format!("invalid instance '{}'", value)))?;
+                    selector.instance = Some(instance);
+                }
+                "serial" => {
+                    if value.len() > SERIAL_MAX_LEN || !value.chars().all(|c| c.is_ascii_hexdigit())
+                    {
+                        return Err(err(format!("invalid serial '{}'", value)));
+                    }
+                    selector.serial = Some(value.to_ascii_lowercase());
+                }
+                k => return Err(err(format!("unknown key '{}'", k))),
+            }
+        }
+
+        Ok(selector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(DeviceSelector::new().to_string(), "*:");
+
+        let s = DeviceSelector::new()
+            .backend(Backend::Libusb)
+            .device(2, 5)
+            .instance(1)
+            .serial("A66AB3");
+        assert_eq!(s.to_string(), "libusb:device=2:5 instance=1 serial=a66ab3");
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let selectors = [
+            DeviceSelector::new(),
+            DeviceSelector::new().serial("a66ab3"),
+            DeviceSelector::new().backend(Backend::Cypress).instance(3),
+            DeviceSelector::from(&DeviceInfo::example()),
+        ];
+
+        for s in selectors {
+            assert_eq!(s.to_string().parse::<DeviceSelector>().unwrap(), s);
+        }
+
+        assert_eq!("".parse::<DeviceSelector>().unwrap(), DeviceSelector::new());
+        assert_eq!(
+            "dummy".parse::<DeviceSelector>().unwrap(),
+            DeviceSelector::new().backend(Backend::Dummy)
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let invalid = [
+            "*:serial=",
+            "usb:",
+            "*:serial=xyz",
+            "*:serial",
+            "*:device=2",
+            "*:device=2:300",
+            "*:instance=-1",
+            "*:colour=blue",
+        ];
+
+        for s in invalid {
+            let err = s.parse::<DeviceSelector>().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Inval, "{}", s);
+            assert!(err.detail().is_some());
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        let info = DeviceInfo::example();
+
+        assert!(DeviceSelector::new().matches(&info));
+        assert!(DeviceSelector::from(&info).matches(&info));
+        assert!(DeviceSelector::new().serial("A66A").matches(&info));
+        assert!(DeviceSelector::new().backend(Backend::Any).matches(&info));
+
+        assert!(!DeviceSelector::new().serial("a66b").matches(&info));
+        assert!(!DeviceSelector::new()
+            .backend(Backend::Cypress)
+            .matches(&info));
+        assert!(!DeviceSelector::new().device(2, 6).matches(&info));
+        assert!(!DeviceSelector::new().instance(1).matches(&info));
+    }
+}
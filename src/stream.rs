@@ -0,0 +1,379 @@
+//! RAII guards for synchronous streams
+//!
+//! [`BladeRF::rx_stream`] and [`BladeRF::tx_stream`] configure the sync
+//! interface and enable the stream's channels, returning a guard that only
+//! exposes operations for its direction and disables the channels on drop.
+
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::atomic::Ordering;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+
+use num_complex::Complex;
+
+use crate::{
+    BladeRF, BladeRfError, Channel, ChannelLayout, Direction, ErrorKind, Format, Metadata, Sample,
+};
+
+/// Synchronous stream configuration
+///
+/// Defaults follow the libbladeRF recommendations: 16 buffers of 8192 samples,
+/// 8 transfers and a 3.5 s timeout, streaming a single channel in SC16 Q11.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StreamConfig {
+    /// Stream both channels of a MIMO board, interleaved
+    pub mimo: bool,
+    pub format: Format,
+    pub num_buffers: u32,
+    /// Buffer size in samples, must be a multiple of 1024
+    pub buffer_size: u32,
+    pub num_transfers: u32,
+    /// Stream timeout in milliseconds
+    pub timeout_ms: u32,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            mimo: false,
+            format: Format::Sc16Q11,
+            num_buffers: 16,
+            buffer_size: 8192,
+            num_transfers: 8,
+            timeout_ms: 3500,
+        }
+    }
+}
+
+impl StreamConfig {
+    /// Channel layout for a stream in the provided direction
+    pub fn layout(&self, direction: Direction) -> ChannelLayout {
+        match (direction, self.mimo) {
+            (Direction::Rx, false) => ChannelLayout::RxX1,
+            (Direction::Rx, true) => ChannelLayout::RxX2,
+            (Direction::Tx, false) => ChannelLayout::TxX1,
+            (Direction::Tx, true) => ChannelLayout::TxX2,
+        }
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for super::Rx {}
+    impl Sealed for super::Tx {}
+}
+
+/// Stream direction marker, implemented by [`Rx`] and [`Tx`]
+pub trait StreamDirection: private::Sealed {
+    const DIRECTION: Direction;
+}
+
+/// Receive stream marker
+#[derive(Debug)]
+pub enum Rx {}
+
+impl StreamDirection for Rx {
+    const DIRECTION: Direction = Direction::Rx;
+}
+
+/// Transmit stream marker
+#[derive(Debug)]
+pub enum Tx {}
+
+impl StreamDirection for Tx {
+    const DIRECTION: Direction = Direction::Tx;
+}
+
+/// Device held by a stream, borrowed or shared with the stream's owner
+pub(crate) enum DeviceHandle<'a> {
+    Borrowed(&'a BladeRF),
+    #[cfg(feature = "async")]
+    Shared(Arc<BladeRF>),
+}
+
+impl Deref for DeviceHandle<'_> {
+    type Target = BladeRF;
+
+    fn deref(&self) -> &BladeRF {
+        match self {
+            Self::Borrowed(device) => device,
+            #[cfg(feature = "async")]
+            Self::Shared(device) => device,
+        }
+    }
+}
+
+/// Active synchronous stream, disabling its channels when dropped
+///
+/// Only one stream per direction may be active on a device.
+pub struct Stream<'a, D: StreamDirection> {
+    device: DeviceHandle<'a>,
+    config: StreamConfig,
+    layout: ChannelLayout,
+    enabled: bool,
+    /// Reused by the f32 conversions, in the stream format
+    scratch: Scratch,
+    _direction: PhantomData<D>,
+}
+
+/// Conversion buffer for the stream's sample type
+#[derive(Default)]
+struct Scratch {
+    sc16: Vec<Complex<i16>>,
+    sc8: Vec<Complex<i8>>,
+}
+
+/// Grow a scratch buffer to at least `len` samples
+fn reserve<T: Sample>(buffer: &mut Vec<T>, len: usize) -> &mut [T] {
+    if buffer.len() < len {
+        buffer.resize(len, T::default());
+    }
+    buffer
+}
+
+/// Synchronous receive stream, see [`BladeRF::rx_stream`]
+pub type RxStream<'a> = Stream<'a, Rx>;
+
+/// Synchronous transmit stream, see [`BladeRF::tx_stream`]
+pub type TxStream<'a> = Stream<'a, Tx>;
+
+impl<'a, D: StreamDirection> Stream<'a, D> {
+    /// Configure the sync interface and enable the stream's channels
+    pub(crate) fn new(
+        device: DeviceHandle<'a>,
+        config: StreamConfig,
+    ) -> Result<Self, BladeRfError> {
+        // Claims the direction, failing if another stream is active
+        let layout = config.layout(D::DIRECTION);
+        device.sync_config(
+            layout,
+            config.format,
+            config.num_buffers,
+            config.buffer_size,
+            Some(config.num_transfers),
+            config.timeout_ms,
+        )?;
+
+        // From here drop disables any channels enabled before a failure
+        let stream = Self {
+            device,
+            config,
+            layout,
+            enabled: true,
+            scratch: Scratch::default(),
+            _direction: PhantomData,
+        };
+
+        for channel in stream.channels() {
+            stream.device.enable_module(channel, true)?;
+        }
+
+        Ok(stream)
+    }
+
+    /// Device the stream runs on
+    #[cfg(feature = "async")]
+    pub(crate) fn device(&self) -> &BladeRF {
+        &self.device
+    }
+
+    /// Channels enabled by this stream
+    pub fn channels(&self) -> impl Iterator<Item = Channel> {
+        self.layout.channels()
+    }
+
+    pub fn layout(&self) -> ChannelLayout {
+        self.layout
+    }
+
+    pub fn format(&self) -> Format {
+        self.config.format
+    }
+
+    pub fn config(&self) -> &StreamConfig {
+        &self.config
+    }
+
+    /// Fetch the current timestamp of the stream's direction
+    pub fn timestamp(&self) -> Result<u64, BladeRfError> {
+        self.device.get_timestamp(D::DIRECTION)
+    }
+
+    /// Disable the stream's channels, reporting any failure
+    ///
+    /// Dropping the stream does the same but ignores errors.
+    pub fn close(mut self) -> Result<(), BladeRfError> {
+        self.disable()
+    }
+
+    fn disable(&mut self) -> Result<(), BladeRfError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        self.enabled = false;
+        self.device.streams[D::DIRECTION as usize].store(false, Ordering::SeqCst);
+
+        let mut res = Ok(());
+        for channel in self.channels() {
+            if let Err(e) = self.device.enable_module(channel, false) {
+                res = res.and(Err(e));
+            }
+        }
+        res
+    }
+
+    /// Error for an f32 conversion on a stream without a plain sample format
+    fn unsupported_f32(&self, op: &'static str) -> BladeRfError {
+        BladeRfError::new(ErrorKind::Inval, op).with_detail(format!(
+            "f32 conversion is not supported for {:?}",
+            self.config.format
+        ))
+    }
+
+    /// Check a buffer holds whole samples for every channel
+    fn check_len(&self, len: usize, op: &'static str) -> Result<(), BladeRfError> {
+        let channels = self.layout.num_channels();
+        if len % channels == 0 {
+            return Ok(());
+        }
+
+        Err(BladeRfError::new(ErrorKind::Inval, op).with_detail(format!(
+            "buffer length {} is not a multiple of {} channels",
+            len, channels
+        )))
+    }
+}
+
+impl RxStream<'_> {
+    /// Receive samples, blocking until the buffer is filled
+    ///
+    /// MIMO streams interleave samples from each channel. The sample type must
+    /// match the stream format.
+    pub fn read<T: Sample>(&mut self, data: &mut [T]) -> Result<(), BladeRfError> {
+        self.check_len(data.len(), "RxStream::read")?;
+        self.device.sync_rx(data, self.config.timeout_ms)
+    }
+
+    /// Receive samples with metadata, requires a metadata format
+    pub fn read_meta<T: Sample>(
+        &mut self,
+        data: &mut [T],
+        meta: &mut Metadata,
+    ) -> Result<(), BladeRfError> {
+        self.check_len(data.len(), "RxStream::read_meta")?;
+        self.device.sync_rx_meta(data, meta, self.config.timeout_ms)
+    }
+
+    /// Receive samples normalized to ±1.0, see [`BladeRF::sync_rx_f32`]
+    ///
+    /// The conversion buffer is kept by the stream, metadata formats are rejected.
+    pub fn read_f32(&mut self, data: &mut [Complex<f32>]) -> Result<(), BladeRfError> {
+        let op = "RxStream::read_f32";
+        self.check_len(data.len(), op)?;
+
+        let (len, timeout) = (data.len(), self.config.timeout_ms);
+        match self.config.format {
+            Format::Sc16Q11 => {
+                let scratch = reserve(&mut self.scratch.sc16, len);
+                self.device.sync_rx_f32(data, scratch, timeout)
+            }
+            Format::Sc8Q7 => {
+                let scratch = reserve(&mut self.scratch.sc8, len);
+                self.device.sync_rx_f32(data, scratch, timeout)
+            }
+            _ => Err(self.unsupported_f32(op)),
+        }
+    }
+}
+
+impl TxStream<'_> {
+    /// Transmit samples, blocking until they are queued
+    ///
+    /// MIMO streams interleave samples for each channel. The sample type must
+    /// match the stream format.
+    pub fn write<T: Sample>(&mut self, data: &[T]) -> Result<(), BladeRfError> {
+        self.check_len(data.len(), "TxStream::write")?;
+        self.device.sync_tx(data, self.config.timeout_ms)
+    }
+
+    /// Transmit samples with metadata, requires a metadata format
+    pub fn write_meta<T: Sample>(
+        &mut self,
+        data: &[T],
+        meta: &mut Metadata,
+    ) -> Result<(), BladeRfError> {
+        self.check_len(data.len(), "TxStream::write_meta")?;
+        self.device.sync_tx_meta(data, meta, self.config.timeout_ms)
+    }
+
+    /// Transmit samples normalized to ±1.0, see [`BladeRF::sync_tx_f32`]
+    ///
+    /// The conversion buffer is kept by the stream, metadata formats are rejected.
+    pub fn write_f32(&mut self, data: &[Complex<f32>]) -> Result<(), BladeRfError> {
+        let op = "TxStream::write_f32";
+        self.check_len(data.len(), op)?;
+
+        let (len, timeout) = (data.len(), self.config.timeout_ms);
+        match self.config.format {
+            Format::Sc16Q11 => {
+                let scratch = reserve(&mut self.scratch.sc16, len);
+                self.device.sync_tx_f32(data, scratch, timeout)
+            }
+            Format::Sc8Q7 => {
+                let scratch = reserve(&mut self.scratch.sc8, len);
+                self.device.sync_tx_f32(data, scratch, timeout)
+            }
+            _ => Err(self.unsupported_f32(op)),
+        }
+    }
+}
+
+impl<D: StreamDirection> Drop for Stream<'_, D> {
+    fn drop(&mut self) {
+        let _ = self.disable();
+    }
+}
+
+impl<D: StreamDirection> Debug for Stream<'_, D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Stream")
+            .field("direction", &D::DIRECTION)
+            .field("layout", &self.layout)
+            .field("config", &self.config)
+            .field("enabled", &self.enabled)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        let config = StreamConfig::default();
+        assert_eq!(config.layout(Direction::Rx), ChannelLayout::RxX1);
+        assert_eq!(config.layout(Direction::Tx), ChannelLayout::TxX1);
+
+        let mimo = StreamConfig {
+            mimo: true,
+            ..Default::default()
+        };
+        assert_eq!(mimo.layout(Direction::Rx), ChannelLayout::RxX2);
+        assert_eq!(mimo.layout(Direction::Tx), ChannelLayout::TxX2);
+    }
+
+    #[test]
+    fn test_reserve() {
+        let mut buffer = Vec::<Complex<i8>>::new();
+        assert_eq!(reserve(&mut buffer, 4).len(), 4);
+
+        // Larger buffers are reused as is
+        let ptr = buffer.as_ptr();
+        assert_eq!(reserve(&mut buffer, 2).len(), 4);
+        assert_eq!(buffer.as_ptr(), ptr);
+    }
+}
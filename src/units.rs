@@ -0,0 +1,463 @@
+//! Unit-safe newtypes for frequency, sample rate, bandwidth and gain
+//!
+//! Values display with SI prefixes (`915 MHz`, `10 Msps`) and parse from
+//! strings such as `"2.4G"`, `"10M"`, `"915MHz"` or `"30 dB"`.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use bladerf_sys::bladerf_rational_rate;
+
+use crate::{BladeRfError, ErrorKind};
+
+/// SI prefixes used for display and parsing
+const PREFIXES: [(&str, f64); 3] = [("G", 1e9), ("M", 1e6), ("k", 1e3)];
+
+/// Write a value using the largest SI prefix that keeps it >= 1
+fn fmt_si(value: f64, unit: &str, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    for (prefix, scale) in PREFIXES {
+        if value.abs() >= scale {
+            return write!(f, "{} {}{}", value / scale, prefix, unit);
+        }
+    }
+    write!(f, "{} {}", value, unit)
+}
+
+/// Strip a case insensitive suffix
+fn strip_suffix_ci<'a>(s: &'a str, suffix: &str) -> Option<&'a str> {
+    let i = s.len().checked_sub(suffix.len())?;
+    match s.is_char_boundary(i) && s[i..].eq_ignore_ascii_case(suffix) {
+        true => Some(&s[..i]),
+        false => None,
+    }
+}
+
+/// Parse a value with an optional SI prefix and unit suffix
+///
+/// Units are case insensitive, so a lowercase `m` is accepted as mega as part
+/// of a unit such as `msps` or `mhz`. A bare `m` (milli) is rejected.
+fn parse_si(s: &str, units: &[&str], op: &'static str) -> Result<f64, BladeRfError> {
+    let err =
+        || BladeRfError::new(ErrorKind::Inval, op).with_detail(format!("invalid value '{}'", s));
+
+    let v = s.trim();
+    let unit = units.iter().find_map(|u| strip_suffix_ci(v, u));
+    let v = unit.unwrap_or(v).trim_end();
+
+    let (number, scale) = match v.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&v[..i], 1e3),
+        Some((i, 'M')) => (&v[..i], 1e6),
+        Some((i, 'm')) if unit.is_some() => (&v[..i], 1e6),
+        Some((i, 'G' | 'g')) => (&v[..i], 1e9),
+        _ => (v, 1.0),
+    };
+
+    let value = number.trim().parse::<f64>().map_err(|_| err())?;
+    if !value.is_finite() {
+        return Err(err());
+    }
+
+    Ok((value * scale).round())
+}
+
+/// Convert a parsed value to an integer type, failing when out of range
+fn parse_int<T: TryFrom<i128>>(s: &str, value: f64, op: &'static str) -> Result<T, BladeRfError> {
+    T::try_from(value as i128).map_err(|_| {
+        BladeRfError::new(ErrorKind::Range, op).with_detail(format!("value '{}' out of range", s))
+    })
+}
+
+/// Serde visitor accepting unit strings or plain numbers
+#[cfg(feature = "serde")]
+struct UnitVisitor<T>(std::marker::PhantomData<T>);
+
+#[cfg(feature = "serde")]
+impl<'de, T: FromStr<Err = BladeRfError>> serde::de::Visitor<'de> for UnitVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a number or a string with units")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<T, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<T, E> {
+        self.visit_str(&v.to_string())
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<T, E> {
+        self.visit_str(&v.to_string())
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<T, E> {
+        self.visit_str(&v.to_string())
+    }
+}
+
+/// Serialize as a unit string, deserialize from a unit string or number
+macro_rules! unit_serde {
+    ($name:ident) => {
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_any(UnitVisitor(std::marker::PhantomData))
+            }
+        }
+    };
+}
+
+macro_rules! unit_type {
+    ($name:ident, $inner:ty, $unit:expr, [$($suffix:expr),*]) => {
+        impl From<$inner> for $name {
+            fn from(v: $inner) -> Self {
+                Self(v)
+            }
+        }
+
+        impl From<$name> for $inner {
+            fn from(v: $name) -> Self {
+                v.0
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                fmt_si(self.0 as f64, $unit, f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = BladeRfError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let op = concat!(stringify!($name), "::from_str");
+                let value = parse_si(s, &[$($suffix),*], op)?;
+                Ok(Self(parse_int(s, value, op)?))
+            }
+        }
+
+        unit_serde!($name);
+    };
+}
+
+/// Frequency in Hz
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct Hertz(pub u64);
+
+impl Hertz {
+    pub const fn hz(v: u64) -> Self {
+        Self(v)
+    }
+
+    pub const fn khz(v: u64) -> Self {
+        Self(v * 1_000)
+    }
+
+    pub const fn mhz(v: u64) -> Self {
+        Self(v * 1_000_000)
+    }
+
+    pub const fn ghz(v: u64) -> Self {
+        Self(v * 1_000_000_000)
+    }
+}
+
+unit_type!(Hertz, u64, "Hz", ["Hz"]);
+
+/// Sample rate in samples per second
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct SampleRate(pub u32);
+
+impl SampleRate {
+    pub const fn sps(v: u32) -> Self {
+        Self(v)
+    }
+
+    pub const fn ksps(v: u32) -> Self {
+        Self(v * 1_000)
+    }
+
+    pub const fn msps(v: u32) -> Self {
+        Self(v * 1_000_000)
+    }
+}
+
+unit_type!(SampleRate, u32, "sps", ["sps", "S/s", "Hz"]);
+
+/// Sample rate with a fractional part, `integer + num / den` samples per second
+///
+/// Owned equivalent of bladerf_rational_rate
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RationalRate {
+    pub integer: u64,
+    pub num: u64,
+    pub den: u64,
+}
+
+impl RationalRate {
+    /// Approximate rate in samples per second
+    pub fn as_f64(&self) -> f64 {
+        match self.den {
+            0 => self.integer as f64,
+            den => self.integer as f64 + self.num as f64 / den as f64,
+        }
+    }
+}
+
+impl From<SampleRate> for RationalRate {
+    fn from(rate: SampleRate) -> Self {
+        Self {
+            integer: rate.0 as u64,
+            num: 0,
+            den: 1,
+        }
+    }
+}
+
+impl From<bladerf_rational_rate> for RationalRate {
+    fn from(r: bladerf_rational_rate) -> Self {
+        Self {
+            integer: r.integer,
+            num: r.num,
+            den: r.den,
+        }
+    }
+}
+
+impl From<RationalRate> for bladerf_rational_rate {
+    fn from(r: RationalRate) -> Self {
+        Self {
+            integer: r.integer,
+            num: r.num,
+            den: r.den,
+        }
+    }
+}
+
+/// Analog filter bandwidth in Hz
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct Bandwidth(pub u32);
+
+impl Bandwidth {
+    pub const fn hz(v: u32) -> Self {
+        Self(v)
+    }
+
+    pub const fn khz(v: u32) -> Self {
+        Self(v * 1_000)
+    }
+
+    pub const fn mhz(v: u32) -> Self {
+        Self(v * 1_000_000)
+    }
+}
+
+unit_type!(Bandwidth, u32, "Hz", ["Hz"]);
+
+/// Gain in dB
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct Decibels(pub i32);
+
+impl From<i32> for Decibels {
+    fn from(v: i32) -> Self {
+        Self(v)
+    }
+}
+
+impl From<Decibels> for i32 {
+    fn from(v: Decibels) -> Self {
+        v.0
+    }
+}
+
+impl Display for Decibels {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} dB", self.0)
+    }
+}
+
+impl FromStr for Decibels {
+    type Err = BladeRfError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let op = "Decibels::from_str";
+        let v = s.trim();
+        let v = strip_suffix_ci(v, "dB").unwrap_or(v);
+
+        let value = v.trim().parse::<f64>().map_err(|_| {
+            BladeRfError::new(ErrorKind::Inval, op).with_detail(format!("invalid value '{}'", s))
+        })?;
+        if !value.is_finite() {
+            return Err(BladeRfError::new(ErrorKind::Inval, op)
+                .with_detail(format!("invalid value '{}'", s)));
+        }
+
+        // Gains are whole dB, rounding would silently change the value
+        if value.fract() != 0.0 {
+            return Err(BladeRfError::new(ErrorKind::Inval, op)
+                .with_detail(format!("'{}' is not a whole number of dB", s)));
+        }
+
+        Ok(Self(parse_int(s, value, op)?))
+    }
+}
+
+unit_serde!(Decibels);
+
+/// Frequency in Hz
+pub const fn hz(v: u64) -> Hertz {
+    Hertz::hz(v)
+}
+
+/// Frequency in kHz
+pub const fn khz(v: u64) -> Hertz {
+    Hertz::khz(v)
+}
+
+/// Frequency in MHz
+pub const fn mhz(v: u64) -> Hertz {
+    Hertz::mhz(v)
+}
+
+/// Frequency in GHz
+pub const fn ghz(v: u64) -> Hertz {
+    Hertz::ghz(v)
+}
+
+/// Sample rate in ksps
+pub const fn ksps(v: u32) -> SampleRate {
+    SampleRate::ksps(v)
+}
+
+/// Sample rate in Msps
+pub const fn msps(v: u32) -> SampleRate {
+    SampleRate::msps(v)
+}
+
+/// Gain in dB
+pub const fn db(v: i32) -> Decibels {
+    Decibels(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constructors() {
+        assert_eq!(mhz(915), Hertz(915_000_000));
+        assert_eq!(ghz(2), Hertz(2_000_000_000));
+        assert_eq!(msps(10), SampleRate(10_000_000));
+        assert_eq!(Bandwidth::khz(1500), Bandwidth(1_500_000));
+        assert_eq!(u64::from(khz(1)), 1_000);
+    }
+
+    #[test]
+    fn test_rational_rate() {
+        let r = RationalRate {
+            integer: 1_000_000,
+            num: 1,
+            den: 3,
+        };
+        assert!((r.as_f64() - 1_000_000.333_333).abs() < 1e-3);
+        assert_eq!(RationalRate::from(bladerf_rational_rate::from(r)), r);
+        assert_eq!(RationalRate::from(msps(2)).as_f64(), 2e6);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(mhz(915).to_string(), "915 MHz");
+        assert_eq!(Hertz(2_400_000_000).to_string(), "2.4 GHz");
+        assert_eq!(Hertz(1_500).to_string(), "1.5 kHz");
+        assert_eq!(Hertz(100).to_string(), "100 Hz");
+        assert_eq!(msps(10).to_string(), "10 Msps");
+        assert_eq!(SampleRate(520_834).to_string(), "520.834 ksps");
+        assert_eq!(Bandwidth::mhz(28).to_string(), "28 MHz");
+        assert_eq!(db(-5).to_string(), "-5 dB");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("2.4G".parse::<Hertz>().unwrap(), Hertz(2_400_000_000));
+        assert_eq!("915MHz".parse::<Hertz>().unwrap(), mhz(915));
+        assert_eq!("915 mhz".parse::<Hertz>().unwrap(), mhz(915));
+        assert_eq!("100k".parse::<Hertz>().unwrap(), khz(100));
+        assert_eq!("433920000".parse::<Hertz>().unwrap(), Hertz(433_920_000));
+        assert_eq!("915e6".parse::<Hertz>().unwrap(), mhz(915));
+        assert_eq!("10M".parse::<SampleRate>().unwrap(), msps(10));
+        assert_eq!(
+            "61.44 Msps".parse::<SampleRate>().unwrap(),
+            SampleRate(61_440_000)
+        );
+        assert_eq!("1.5M".parse::<Bandwidth>().unwrap(), Bandwidth(1_500_000));
+        assert_eq!("30 dB".parse::<Decibels>().unwrap(), db(30));
+        assert_eq!("-5".parse::<Decibels>().unwrap(), db(-5));
+
+        for v in ["", "G", "2.4X", "abc", "inf", "100m"] {
+            assert_eq!(
+                v.parse::<Hertz>().unwrap_err().kind(),
+                ErrorKind::Inval,
+                "{}",
+                v
+            );
+        }
+        assert_eq!("100 msps".parse::<SampleRate>().unwrap(), msps(100));
+        assert_eq!(
+            "30.5 dB".parse::<Decibels>().unwrap_err().kind(),
+            ErrorKind::Inval
+        );
+        assert_eq!("-1M".parse::<Hertz>().unwrap_err().kind(), ErrorKind::Range);
+        assert_eq!(
+            "5G".parse::<SampleRate>().unwrap_err().kind(),
+            ErrorKind::Range
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for f in [
+            Hertz(915_000_000),
+            Hertz(2_400_000_000),
+            Hertz(1_500),
+            Hertz(7),
+        ] {
+            assert_eq!(f.to_string().parse::<Hertz>().unwrap(), f);
+        }
+        for r in [msps(10), SampleRate(520_834)] {
+            assert_eq!(r.to_string().parse::<SampleRate>().unwrap(), r);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        assert_eq!(serde_json::to_string(&mhz(915)).unwrap(), "\"915 MHz\"");
+        assert_eq!(serde_json::to_string(&db(-5)).unwrap(), "\"-5 dB\"");
+
+        assert_eq!(
+            serde_json::from_str::<Hertz>("\"2.4 GHz\"").unwrap(),
+            Hertz(2_400_000_000)
+        );
+        assert_eq!(
+            serde_json::from_str::<Hertz>("915000000").unwrap(),
+            mhz(915)
+        );
+        assert_eq!(
+            serde_json::from_str::<SampleRate>("1.5e6").unwrap(),
+            ksps(1500)
+        );
+        assert_eq!(serde_json::from_str::<Decibels>("-5").unwrap(), db(-5));
+        assert!(serde_json::from_str::<Bandwidth>("\"fast\"").is_err());
+        assert!(serde_json::from_str::<SampleRate>("-1").is_err());
+    }
+}
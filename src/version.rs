@@ -0,0 +1,232 @@
+//! Owned version information and minimum version requirements
+
+use std::cmp::Ordering;
+use std::ffi::CStr;
+use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use bladerf_sys::*;
+
+use crate::{BladeRfError, ErrorKind};
+
+/// Firmware, FPGA or library version
+///
+/// Owned equivalent of bladerf_version. Comparison and ordering use
+/// the numeric components only, the describe string is informational.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Version {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+    /// Version description string
+    pub describe: String,
+}
+
+impl Version {
+    /// Create a version with no description
+    pub fn new(major: u16, minor: u16, patch: u16) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+            describe: String::new(),
+        }
+    }
+
+    /// Copy a raw bladerf_version, including the describe string
+    ///
+    /// # Safety
+    /// `version.describe` must be null or point to a valid null terminated string
+    pub(crate) unsafe fn from_raw(version: &bladerf_version) -> Self {
+        let describe = if version.describe.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(version.describe)
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        Self {
+            major: version.major,
+            minor: version.minor,
+            patch: version.patch,
+            describe,
+        }
+    }
+
+    fn triple(&self) -> (u16, u16, u16) {
+        (self.major, self.minor, self.patch)
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.triple() == other.triple()
+    }
+}
+
+impl Eq for Version {}
+
+impl Hash for Version {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.triple().hash(state)
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.triple().cmp(&other.triple())
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for Version {
+    type Err = BladeRfError;
+
+    /// Parse a `major.minor[.patch]` version string
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || {
+            BladeRfError::new(ErrorKind::Inval, "Version::from_str")
+                .with_detail(format!("invalid version '{}'", s))
+        };
+
+        let mut parts = s.trim().split('.').map(|p| p.parse::<u16>());
+        let major = parts.next().ok_or_else(err)?.map_err(|_| err())?;
+        let minor = parts.next().ok_or_else(err)?.map_err(|_| err())?;
+        let patch = parts.next().unwrap_or(Ok(0)).map_err(|_| err())?;
+        if parts.next().is_some() {
+            return Err(err());
+        }
+
+        Ok(Self::new(major, minor, patch))
+    }
+}
+
+/// Minimum firmware, FPGA and libbladeRF versions required by an application
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MinVersions {
+    pub firmware: Option<Version>,
+    pub fpga: Option<Version>,
+    pub library: Option<Version>,
+}
+
+impl MinVersions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require at least the specified firmware version
+    pub fn firmware(mut self, v: Version) -> Self {
+        self.firmware = Some(v);
+        self
+    }
+
+    /// Require at least the specified FPGA version
+    pub fn fpga(mut self, v: Version) -> Self {
+        self.fpga = Some(v);
+        self
+    }
+
+    /// Require at least the specified libbladeRF version
+    pub fn library(mut self, v: Version) -> Self {
+        self.library = Some(v);
+        self
+    }
+
+    /// Check the provided versions against these requirements
+    ///
+    /// Versions not supplied (`None`) are not checked.
+    pub fn check(
+        &self,
+        firmware: Option<&Version>,
+        fpga: Option<&Version>,
+        library: Option<&Version>,
+    ) -> Result<(), BladeRfError> {
+        let checks = [
+            ("firmware", ErrorKind::UpdateFw, &self.firmware, firmware),
+            ("FPGA", ErrorKind::UpdateFpga, &self.fpga, fpga),
+            ("libbladeRF", ErrorKind::Unsupported, &self.library, library),
+        ];
+
+        for (name, kind, required, actual) in checks {
+            if let (Some(required), Some(actual)) = (required, actual) {
+                if actual < required {
+                    return Err(BladeRfError::new(kind, "MinVersions::check").with_detail(
+                        format!(
+                            "{} version {} is older than required {}",
+                            name, actual, required
+                        ),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ordering() {
+        let mut a = Version::new(2, 4, 1);
+        a.describe = "2.4.1-git-1234abcd".to_string();
+
+        assert_eq!(a, Version::new(2, 4, 1));
+        assert!(a < Version::new(2, 5, 0));
+        assert!(a > Version::new(2, 3, 9));
+        assert!(Version::new(0, 15, 0) < Version::new(1, 0, 0));
+        assert_eq!(a.to_string(), "2.4.1");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("2.4.1".parse::<Version>().unwrap(), Version::new(2, 4, 1));
+        assert_eq!("0.15".parse::<Version>().unwrap(), Version::new(0, 15, 0));
+
+        for s in ["", "2", "2.x", "2.4.1.0", "-1.0"] {
+            assert!(s.parse::<Version>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_min_versions() {
+        let min = MinVersions::new()
+            .firmware(Version::new(2, 4, 0))
+            .fpga(Version::new(0, 15, 0));
+
+        let fw_ok = Version::new(2, 4, 0);
+        let fw_old = Version::new(2, 3, 2);
+        let fpga_old = Version::new(0, 14, 0);
+
+        assert!(min.check(Some(&fw_ok), None, None).is_ok());
+        assert!(min.check(None, None, Some(&Version::new(1, 0, 0))).is_ok());
+
+        let err = min.check(Some(&fw_old), None, None).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UpdateFw);
+        assert_eq!(
+            err.detail(),
+            Some("firmware version 2.3.2 is older than required 2.4.0")
+        );
+
+        let err = min.check(Some(&fw_ok), Some(&fpga_old), None).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UpdateFpga);
+        assert!(err.detail().unwrap().contains("FPGA"));
+    }
+}
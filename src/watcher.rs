@@ -0,0 +1,238 @@
+//! Hotplug notifications for attached devices
+//!
+//! libbladeRF has no hotplug API, so [`DeviceWatcher`] polls the device list
+//! on a background thread and reports changes by serial number.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{BladeRF, BladeRfError, DeviceInfo};
+
+/// Device attach / detach event
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DeviceEvent {
+    /// A device was attached (or was present when the watcher started)
+    Attached(DeviceInfo),
+    /// The device with the provided serial was detached
+    Detached(String),
+}
+
+/// Source of device lists for a [`DeviceWatcher`]
+pub trait DeviceSource: Send + 'static {
+    fn list(&mut self) -> Result<Vec<DeviceInfo>, BladeRfError>;
+}
+
+impl<F> DeviceSource for F
+where
+    F: FnMut() -> Result<Vec<DeviceInfo>, BladeRfError> + Send + 'static,
+{
+    fn list(&mut self) -> Result<Vec<DeviceInfo>, BladeRfError> {
+        self()
+    }
+}
+
+/// Tracks the set of attached devices, producing events on change
+#[derive(Clone, Debug, Default)]
+pub struct DeviceTracker {
+    attached: HashMap<String, DeviceInfo>,
+}
+
+impl DeviceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update with the latest device list, returning detach then attach events
+    pub fn update(&mut self, devices: Vec<DeviceInfo>) -> Vec<DeviceEvent> {
+        let mut current: HashMap<String, DeviceInfo> = HashMap::new();
+        let mut attached = vec![];
+
+        for d in devices {
+            if !self.attached.contains_key(&d.serial) && !current.contains_key(&d.serial) {
+                attached.push(DeviceEvent::Attached(d.clone()));
+            }
+            current.insert(d.serial.clone(), d);
+        }
+
+        let mut detached: Vec<_> = self
+            .attached
+            .keys()
+            .filter(|s| !current.contains_key(*s))
+            .cloned()
+            .collect();
+        detached.sort();
+
+        self.attached = current;
+
+        detached
+            .into_iter()
+            .map(DeviceEvent::Detached)
+            .chain(attached)
+            .collect()
+    }
+
+    /// Fetch currently attached devices
+    pub fn attached(&self) -> impl Iterator<Item = &DeviceInfo> {
+        self.attached.values()
+    }
+}
+
+/// Background watcher reporting device attach and detach events
+///
+/// Devices present on the first poll are reported as attached. Polls where
+/// enumeration fails are skipped so transient errors do not cause detach events.
+/// The polling thread is stopped when the watcher is dropped.
+pub struct DeviceWatcher {
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DeviceWatcher {
+    /// Watch for devices, delivering events over a channel
+    pub fn new(interval: Duration) -> (Self, Receiver<DeviceEvent>) {
+        let (tx, rx) = mpsc::channel();
+
+        let w = Self::with_callback(interval, move |e| {
+            // Receiver may be dropped before the watcher, nothing to do
+            let _ = tx.send(e);
+        });
+
+        (w, rx)
+    }
+
+    /// Watch for devices, delivering events to a callback on the polling thread
+    pub fn with_callback<F>(interval: Duration, callback: F) -> Self
+    where
+        F: FnMut(DeviceEvent) + Send + 'static,
+    {
+        Self::with_source(BladeRF::get_device_list, interval, callback)
+    }
+
+    /// Watch for devices listed by the provided source
+    pub fn with_source<S, F>(mut source: S, interval: Duration, mut callback: F) -> Self
+    where
+        S: DeviceSource,
+        F: FnMut(DeviceEvent) + Send + 'static,
+    {
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+        let handle = thread::spawn(move || {
+            let mut tracker = DeviceTracker::new();
+
+            loop {
+                if let Ok(devices) = source.list() {
+                    for e in tracker.update(devices) {
+                        callback(e);
+                    }
+                }
+
+                // Wait for the next poll, exiting when stopped or the watcher is dropped
+                match stop_rx.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => (),
+                    _ => break,
+                }
+            }
+        });
+
+        Self {
+            stop: Some(stop_tx),
+            handle: Some(handle),
+        }
+    }
+
+    /// Stop watching, waiting for the polling thread to exit
+    pub fn stop(self) {
+        drop(self)
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::ErrorKind;
+
+    fn device(serial: &str) -> DeviceInfo {
+        DeviceInfo {
+            serial: serial.to_string(),
+            ..DeviceInfo::example()
+        }
+    }
+
+    #[test]
+    fn test_tracker_diff() {
+        let mut t = DeviceTracker::new();
+
+        assert_eq!(
+            t.update(vec![device("aa"), device("bb")]),
+            vec![
+                DeviceEvent::Attached(device("aa")),
+                DeviceEvent::Attached(device("bb"))
+            ]
+        );
+
+        assert_eq!(t.update(vec![device("bb"), device("aa")]), vec![]);
+
+        assert_eq!(
+            t.update(vec![device("bb"), device("cc")]),
+            vec![
+                DeviceEvent::Detached("aa".to_string()),
+                DeviceEvent::Attached(device("cc"))
+            ]
+        );
+
+        assert_eq!(
+            t.update(vec![]),
+            vec![
+                DeviceEvent::Detached("bb".to_string()),
+                DeviceEvent::Detached("cc".to_string())
+            ]
+        );
+        assert_eq!(t.attached().count(), 0);
+    }
+
+    #[test]
+    fn test_watcher_scripted() {
+        let mut script: VecDeque<Result<Vec<DeviceInfo>, BladeRfError>> = VecDeque::from(vec![
+            Ok(vec![device("aa")]),
+            Ok(vec![device("aa"), device("bb")]),
+            Err(BladeRfError::new(ErrorKind::Io, "bladerf_get_device_list")),
+            Ok(vec![device("bb")]),
+        ]);
+        let source = move || script.pop_front().unwrap_or_else(|| Ok(vec![device("bb")]));
+
+        let (tx, rx) = mpsc::channel();
+        let watcher = DeviceWatcher::with_source(source, Duration::from_millis(1), move |e| {
+            tx.send(e).unwrap();
+        });
+
+        let events: Vec<_> = (0..3)
+            .map(|_| rx.recv_timeout(Duration::from_secs(5)).unwrap())
+            .collect();
+        watcher.stop();
+
+        assert_eq!(
+            events,
+            vec![
+                DeviceEvent::Attached(device("aa")),
+                DeviceEvent::Attached(device("bb")),
+                DeviceEvent::Detached("aa".to_string()),
+            ]
+        );
+        assert!(rx.recv().is_err());
+    }
+}